@@ -0,0 +1,205 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use quadrata::{generate, logical_solve, parse_grid, symbol, to_81, Puzzle, QuadrataError};
+
+const USAGE: &str = "\
+Usage: quadrata <command> [options] [files...]
+
+Puzzles are read from the files (or stdin if none are given), either one
+puzzle per line or as grids over several lines. A line is one cell per
+character (1-9 then A-W for grids up to 32x32, with 0 or . for blanks) or
+comma-separated numbers. Grids over several lines have --size rows (9 if
+not given), ignoring spaces and |, - or + separators. Only plain sudoku can
+be read; variant puzzles need the library.
+
+Commands:
+  solve               print the solution of each puzzle
+  check               report if each puzzle has a unique solution
+  rate                print the difficulty of each puzzle
+  path                print the steps of a logical solve of each puzzle
+  generate [count]    generate new puzzles of --size (--seed <n> to reproduce them)
+  convert             reformat puzzles (--to line|grid)
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Err(USAGE.to_string());
+    };
+    if !["solve", "check", "rate", "path", "generate", "convert"].contains(&command.as_str()) {
+        return Err(format!("unknown command: {}\n\n{}", command, USAGE));
+    }
+    let mut seed: u32 = 1;
+    let mut size: usize = 9;
+    let mut format = "line".to_string();
+    let mut positional = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = rest.next().ok_or("--seed needs a value")?;
+                seed = value.parse().map_err(|_| format!("bad seed: {}", value))?;
+            }
+            "--size" => {
+                let value = rest.next().ok_or("--size needs a value")?;
+                size = value.parse().map_err(|_| format!("bad size: {}", value))?;
+            }
+            "--to" => {
+                format = rest.next().ok_or("--to needs a value")?.clone();
+                if format != "line" && format != "grid" {
+                    return Err(format!("unknown format: {}", format));
+                }
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if command == "generate" {
+        let count = match positional.first() {
+            Some(count) => count.parse().map_err(|_| format!("bad count: {}", count))?,
+            None => 1,
+        };
+        for n in 0..count {
+            let puzzle = generate(size, seed.wrapping_add(n)).map_err(|e| e.to_string())?;
+            println!("{}", to_81(&puzzle));
+        }
+        return Ok(());
+    }
+
+    for text in read_puzzles(&positional, size)? {
+        match command.as_str() {
+            "check" => match parse_grid(&text).and_then(|grid| Puzzle::from_grid(&grid)) {
+                Ok(_) => println!("{} unique", text),
+                Err(QuadrataError::MultipleSolutions { .. }) => println!("{} multiple", text),
+                Err(QuadrataError::NoSolution) => println!("{} none", text),
+                Err(e) => return Err(e.to_string()),
+            },
+            "solve" => {
+                let puzzle = load(&text)?;
+                println!("{}", digits(&puzzle.solution()));
+            }
+            "rate" => {
+                let puzzle = load(&text)?;
+                println!("{} {}", text, puzzle.difficulty());
+            }
+            "path" => {
+                let puzzle = load(&text)?;
+                println!("{}", text);
+                for step in logical_solve(&puzzle) {
+                    for (cell, value) in step.values {
+                        let (row, col) = puzzle.row_col(cell);
+                        if value.count_ones() == 1 {
+                            let digit = symbol(value.trailing_zeros() as usize + 1);
+                            println!("r{}c{}={} ({})", row + 1, col + 1, digit, step.strategy);
+                        } else {
+                            let candidates: String = (0..puzzle.size)
+                                .filter(|v| value & (1 << v) != 0)
+                                .map(|v| symbol(v + 1))
+                                .collect();
+                            println!("r{}c{}:{} ({})", row + 1, col + 1, candidates, step.strategy);
+                        }
+                    }
+                }
+            }
+            "convert" => {
+                let grid = parse_grid(&text).map_err(|e| format!("{}: {}", text, e))?;
+                let puzzle = Puzzle::raw_from_grid(&grid);
+                if format == "grid" {
+                    println!("{}", grid_text(&puzzle));
+                } else {
+                    println!("{}", to_81(&puzzle));
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+fn load(text: &str) -> Result<Puzzle, String> {
+    parse_grid(text).and_then(|grid| Puzzle::from_grid(&grid)).map_err(|e| format!("{}: {}", text, e))
+}
+
+/// Split the input files into puzzles, accepting one puzzle per line or grids of `size` rows
+/// over several lines (where spaces and `|`, `-` or `+` separators are ignored).
+fn read_puzzles(files: &[String], size: usize) -> Result<Vec<String>, String> {
+    let mut input = String::new();
+    if files.is_empty() {
+        io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+    }
+    for file in files {
+        input.push_str(&fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?);
+        input.push('\n');
+    }
+
+    let cells = size * size;
+    let mut puzzles = Vec::new();
+    let mut partial = String::new();
+    for line in input.lines() {
+        // a line with a whole grid of cells (of any size for one without spaces) is a puzzle
+        let length = line.chars().count();
+        let whole = length == cells || (length > size && is_square(length) && !line.contains(' '));
+        if partial.is_empty() && (whole || line.contains(',')) {
+            puzzles.push(line.to_string());
+            continue;
+        }
+        partial.extend(line.chars().filter(|c| !" \t|-+".contains(*c)));
+        if partial.chars().count() >= cells {
+            puzzles.push(partial.chars().take(cells).collect());
+            partial.clear();
+        }
+    }
+    if !partial.is_empty() {
+        return Err(format!("incomplete puzzle at end of input: {}", partial));
+    }
+    Ok(puzzles)
+}
+
+fn is_square(length: usize) -> bool {
+    let side = (length as f64).sqrt() as usize;
+    side * side == length
+}
+
+fn digits(grid: &[u8]) -> String {
+    grid.iter().map(|v| if *v == 0 { '0' } else { symbol(*v as usize) }).collect()
+}
+
+/// Lay out the grid over multiple lines with separators between the boxes.
+fn grid_text(puzzle: &Puzzle) -> String {
+    let size = puzzle.size;
+    let box_of = |cell: usize| puzzle.geometry().unit_in(2, cell);
+    let mut lines = Vec::new();
+    for (r, row) in puzzle.to_grid().chunks(size).enumerate() {
+        let mut line = String::new();
+        let mut divider = String::new();
+        for (c, v) in row.iter().enumerate() {
+            let cell = r * size + c;
+            if c > 0 {
+                let new_box = box_of(cell) != box_of(cell - 1);
+                line.push_str(if new_box { " | " } else { " " });
+                divider.push_str(if new_box { "-+-" } else { "-" });
+            }
+            line.push(if *v == 0 { '.' } else { symbol(*v as usize) });
+            divider.push('-');
+        }
+        if r > 0 && box_of(r * size) != box_of((r - 1) * size) {
+            lines.push(divider);
+        }
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
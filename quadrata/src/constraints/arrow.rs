@@ -0,0 +1,163 @@
+use crate::constraints::{distinct_bounds, Constraint};
+use crate::{Candidates, Puzzle};
+
+/// An arrow: the digits along `path` add up to the number in the circle.
+///
+/// A circle of several cells (a pill) reads as a multi-digit number in the order of `circle`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub circle: Vec<usize>,
+    pub path: Vec<usize>,
+}
+
+impl Arrow {
+    /// Split the path by box since the digits in each part can't repeat.
+    fn path_groups(&self, board: &Puzzle) -> Vec<Vec<usize>> {
+        let box_of = |cell: usize| board.geometry().unit_in(2, cell);
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for cell in &self.path {
+            let region = box_of(*cell);
+            match groups.iter_mut().find(|g| region.is_some() && box_of(g[0]) == region) {
+                Some(group) => group.push(*cell),
+                None => groups.push(vec![*cell]),
+            }
+        }
+        groups
+    }
+
+    /// Every number the circle could read given its candidates.
+    fn circle_numbers(&self, values: &[Candidates]) -> Vec<(u32, Vec<u32>)> {
+        let mut numbers = vec![(0, Vec::new())];
+        for cell in &self.circle {
+            let mut next = Vec::new();
+            for (number, digits) in &numbers {
+                for val in (0..Candidates::BITS).filter(|v| values[*cell] & (1 << v) != 0) {
+                    let mut digits = digits.clone();
+                    digits.push(val);
+                    next.push((number * 10 + val + 1, digits));
+                }
+            }
+            numbers = next;
+        }
+        numbers
+    }
+}
+
+impl Constraint for Arrow {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let groups = self.path_groups(board);
+        let Some(bounds) = groups
+            .iter()
+            .map(|g| distinct_bounds(g, values, 0))
+            .collect::<Option<Vec<(u32, u32)>>>()
+        else {
+            return;
+        };
+        let low: u32 = bounds.iter().map(|b| b.0).sum();
+        let high: u32 = bounds.iter().map(|b| b.1).sum();
+
+        let numbers: Vec<(u32, Vec<u32>)> = self
+            .circle_numbers(values)
+            .into_iter()
+            .filter(|(n, _)| *n >= low && *n <= high)
+            .collect();
+        let mut circle_allowed: Vec<Candidates> = vec![0; self.circle.len()];
+        for (_, digits) in &numbers {
+            for (allowed, val) in circle_allowed.iter_mut().zip(digits) {
+                *allowed |= 1 << val;
+            }
+        }
+        for (cell, allowed) in self.circle.iter().zip(circle_allowed) {
+            if !board.solved[*cell] {
+                values[*cell] &= allowed;
+            }
+        }
+        let (Some(n_low), Some(n_high)) = (
+            numbers.iter().map(|n| n.0).min(),
+            numbers.iter().map(|n| n.0).max(),
+        ) else {
+            return;
+        };
+
+        // a digit in the path only works if the rest of the path can make up the difference
+        for (g, group) in groups.iter().enumerate() {
+            let (other_low, other_high) = bounds
+                .iter()
+                .enumerate()
+                .filter(|(h, _)| *h != g)
+                .fold((0, 0), |acc, (_, b)| (acc.0 + b.0, acc.1 + b.1));
+            for cell in group.iter().filter(|c| !board.solved[**c]) {
+                let rest: Vec<usize> = group.iter().copied().filter(|c| c != cell).collect();
+                let mut allowed = 0;
+                for val in (0..board.size as u32).filter(|v| values[*cell] & (1 << v) != 0) {
+                    let Some((rest_low, rest_high)) = distinct_bounds(&rest, values, 1 << val) else {
+                        continue;
+                    };
+                    let (sum_low, sum_high) = (val + 1 + other_low + rest_low, val + 1 + other_high + rest_high);
+                    if sum_low <= n_high && sum_high >= n_low {
+                        allowed |= 1 << val;
+                    }
+                }
+                values[*cell] &= allowed;
+            }
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        if self.circle.iter().any(|c| !board.solved[*c]) {
+            return Vec::new();
+        }
+        let number = self
+            .circle
+            .iter()
+            .fold(0, |n, c| n * 10 + board.values[*c].trailing_zeros() + 1);
+        let solved: Vec<usize> = self.path.iter().copied().filter(|c| board.solved[*c]).collect();
+        let total: u32 = solved.iter().map(|c| board.values[*c].trailing_zeros() + 1).sum();
+        let n_open = (self.path.len() - solved.len()) as u32;
+        // every open cell needs at least a 1
+        if (n_open == 0 && total != number) || total + n_open > number {
+            let mut bad_cells = self.circle.clone();
+            bad_cells.extend(solved);
+            return bad_cells;
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrow_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        // a two cell arrow in one box is at least 1 + 2 and at most 9 so can't hold a 9
+        let arrow = Arrow { circle: vec![0], path: vec![1, 2] };
+        let mut values = vec![0b111111111; 81];
+        arrow.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b111111100, 0b011111111, 0b011111111]);
+
+        // leaving the box the path can repeat digits so 1 + 1 = 2 is possible
+        let arrow = Arrow { circle: vec![0], path: vec![2, 3] };
+        let mut values = vec![0b111111111; 81];
+        arrow.prune(&board, &mut values);
+        assert_eq!(&values[..4], &[0b111111110, 0b111111111, 0b011111111, 0b011111111]);
+
+        // a pill with a 1 in front makes at least 10
+        let arrow = Arrow { circle: vec![0, 1], path: vec![9, 10, 11] };
+        let mut values = vec![0b111111111; 81];
+        values[0] = 1;
+        arrow.prune(&board, &mut values);
+        assert_eq!(&values[..2], &[0b1, 0b111111111]);
+        assert_eq!(values[9], 0b111111111);
+    }
+
+    #[test]
+    fn test_arrow_conflicts() {
+        let board = Puzzle::raw_from_grid(&[&[5, 2, 3, 4][..], &[0; 77]].concat());
+        assert_eq!(Arrow { circle: vec![0], path: vec![1, 2] }.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(Arrow { circle: vec![0], path: vec![1, 3] }.conflicts(&board), vec![0, 1, 3]);
+        assert_eq!(Arrow { circle: vec![0], path: vec![3, 9, 10] }.conflicts(&board), vec![0, 3]);
+        assert_eq!(Arrow { circle: vec![2, 0], path: vec![9] }.conflicts(&board), Vec::<usize>::new());
+    }
+}
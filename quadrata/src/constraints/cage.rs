@@ -0,0 +1,138 @@
+use crate::constraints::{distinct_sum_allowed, restrict, Constraint};
+use crate::{Candidates, Puzzle};
+
+/// A killer cage: the digits in the cells don't repeat and add up to `sum`.
+///
+/// A `sum` of 0 means the cage has no total and only stops digits repeating.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+/// The total of the digits in a bit mask of values.
+fn mask_sum(mask: Candidates) -> u32 {
+    (0..Candidates::BITS).filter(|val| mask & (1 << val) != 0).map(|val| val + 1).sum()
+}
+
+impl Cage {
+    /// Prune with only the bounds of the total, for grids with too many digits to go through
+    /// every set of them.
+    fn prune_bounds(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let (solved, open): (Vec<usize>, Vec<usize>) =
+            self.cells.iter().partition(|c| board.solved[**c]);
+        let used = solved.iter().fold(0, |acc, c| acc | values[*c]);
+        if self.sum == 0 {
+            let allowed: Vec<Candidates> = open.iter().map(|c| values[*c] & !used).collect();
+            restrict(board, values, &open, &allowed);
+            return;
+        }
+        let rest = self.sum.checked_sub(mask_sum(used));
+        match rest.and_then(|rest| distinct_sum_allowed(&open, values, used, rest)) {
+            Some(allowed) => restrict(board, values, &open, &allowed),
+            None => restrict(board, values, &open, &vec![0; open.len()]),
+        }
+    }
+}
+
+impl Constraint for Cage {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        if board.size > 16 {
+            self.prune_bounds(board, values);
+            return;
+        }
+        let candidates: Vec<Candidates> = self.cells.iter().map(|i| values[*i]).collect();
+        let n_cells = self.cells.len();
+        let n_masks = 1usize << board.size;
+
+        // `good[mask]` if the cells after the first `mask.count_ones()` can be filled in
+        // without reusing the digits in `mask` to hit the total
+        let mut good = vec![false; n_masks];
+        for mask in (0..n_masks).rev() {
+            let placed = mask.count_ones() as usize;
+            if placed > n_cells {
+                continue;
+            }
+            if placed == n_cells {
+                good[mask] = self.sum == 0 || mask_sum(mask as Candidates) == self.sum;
+                continue;
+            }
+            let open = candidates[placed] & !(mask as Candidates);
+            good[mask] = (0..board.size).any(|v| open & (1 << v) != 0 && good[mask | (1 << v)]);
+        }
+
+        // walk forward from the empty mask keeping the digits that lead to a good mask
+        let mut reachable = vec![false; n_masks];
+        reachable[0] = true;
+        let mut allowed: Vec<Candidates> = vec![0; n_cells];
+        for mask in 0..n_masks {
+            let placed = mask.count_ones() as usize;
+            if !reachable[mask] || placed >= n_cells {
+                continue;
+            }
+            let open = candidates[placed] & !(mask as Candidates);
+            for v in (0..board.size).filter(|v| open & (1 << v) != 0) {
+                if good[mask | (1 << v)] {
+                    reachable[mask | (1 << v)] = true;
+                    allowed[placed] |= 1 << v;
+                }
+            }
+        }
+        for (cell, allowed) in self.cells.iter().zip(allowed) {
+            if !board.solved[*cell] {
+                values[*cell] &= allowed;
+            }
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let solved: Vec<usize> = self.cells.iter().copied().filter(|i| board.solved[*i]).collect();
+        let mut bad_cells: Vec<usize> = solved
+            .iter()
+            .copied()
+            .filter(|i| solved.iter().any(|j| i != j && board.values[*i] == board.values[*j]))
+            .collect();
+        if self.sum != 0 {
+            let total: u32 = solved.iter().map(|i| board.values[*i].trailing_zeros() + 1).sum();
+            let n_open = (self.cells.len() - solved.len()) as u32;
+            // every open cell needs at least a 1 so the total can't be too high already
+            if (n_open == 0 && total != self.sum) || total + n_open > self.sum {
+                bad_cells = solved;
+            }
+        }
+        bad_cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cage_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let mut values = vec![0b111111111; 81];
+        Cage { cells: vec![0, 1], sum: 3 }.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b11, 0b11, 0b111111111]);
+
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b100;
+        Cage { cells: vec![0, 1, 2], sum: 10 }.prune(&board, &mut values);
+        // 3 + {1, 6}, {2, 5} or {6, 1}, {5, 2}
+        assert_eq!(&values[..3], &[0b100, 0b110011, 0b110011]);
+
+        let mut values = vec![0b111111111; 81];
+        Cage { cells: vec![0, 1], sum: 0 }.prune(&board, &mut values);
+        assert_eq!(&values[..2], &[0b111111111, 0b111111111]);
+    }
+
+    #[test]
+    fn test_cage_conflicts() {
+        let board = Puzzle::raw_from_grid(&[&[1, 2, 3, 3][..], &[0; 77]].concat());
+        assert_eq!(Cage { cells: vec![0, 1, 2], sum: 6 }.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(Cage { cells: vec![0, 1, 2], sum: 7 }.conflicts(&board), vec![0, 1, 2]);
+        assert_eq!(Cage { cells: vec![0, 1, 4], sum: 3 }.conflicts(&board), vec![0, 1]);
+        assert_eq!(Cage { cells: vec![0, 1, 4], sum: 5 }.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(Cage { cells: vec![2, 3, 4], sum: 0 }.conflicts(&board), vec![2, 3]);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::constraints::Constraint;
+use crate::{Candidates, Puzzle};
+
+/// A clue on the edge between two orthogonally neighboring cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeKind {
+    /// A white Kropki dot: the digits are consecutive.
+    White,
+    /// A black Kropki dot: one digit is double the other.
+    Black,
+    /// The digits add up to 10.
+    X,
+    /// The digits add up to 5.
+    V,
+    /// The first cell's digit is greater than the second's.
+    Greater,
+}
+
+impl EdgeKind {
+    fn allows(&self, a: u32, b: u32) -> bool {
+        match self {
+            EdgeKind::White => a + 1 == b || b + 1 == a,
+            EdgeKind::Black => a == 2 * b || b == 2 * a,
+            EdgeKind::X => a + b == 10,
+            EdgeKind::V => a + b == 5,
+            EdgeKind::Greater => a > b,
+        }
+    }
+}
+
+/// A kind of clue and whether it has to hold (or has to not hold) between two cells.
+type Rule = (EdgeKind, bool);
+
+/// Clues between neighboring cells: Kropki dots, XV sums and greater-than signs.
+///
+/// Any kind in `negative` is a clue by its absence too: unmarked neighbors can't fit it (so
+/// a negative `White` with no white dots is the non-consecutive rule). A pair marked with one
+/// of the negative kinds only has to fit its own marks, since e.g. a 1 and 2 could take either
+/// Kropki dot.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Edges {
+    pub marks: Vec<(usize, usize, EdgeKind)>,
+    pub negative: Vec<EdgeKind>,
+}
+
+impl Edges {
+    /// Neighboring cells can't have consecutive digits.
+    pub fn non_consecutive() -> Edges {
+        Edges { marks: Vec::new(), negative: vec![EdgeKind::White] }
+    }
+
+    /// Every pair of cells with a clue and whether each of their clues has to hold or not.
+    fn rules(&self, board: &Puzzle) -> Vec<(usize, usize, Vec<Rule>)> {
+        let mut rules: Vec<(usize, usize, Vec<Rule>)> =
+            self.marks.iter().map(|(a, b, kind)| (*a, *b, vec![(*kind, true)])).collect();
+        if self.negative.is_empty() {
+            return rules;
+        }
+        for cell in 0..board.values.len() {
+            let right = board.geometry().offset(cell, (0, 1));
+            let below = board.geometry().offset(cell, (1, 0));
+            for other in [right, below].into_iter().flatten().filter(|other| *other != cell) {
+                let marked = self
+                    .marks
+                    .iter()
+                    .filter(|(a, b, _)| (*a, *b) == (cell, other) || (*a, *b) == (other, cell))
+                    .any(|(_, _, kind)| self.negative.contains(kind));
+                if !marked {
+                    rules.push((cell, other, self.negative.iter().map(|k| (*k, false)).collect()));
+                }
+            }
+        }
+        rules
+    }
+}
+
+fn fits(rules: &[Rule], a: u32, b: u32) -> bool {
+    rules.iter().all(|(kind, holds)| kind.allows(a, b) == *holds)
+}
+
+impl Constraint for Edges {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        for (a, b, rules) in self.rules(board) {
+            // keep the digits of each cell that work with at least one digit of the other
+            for (cell, other) in [(a, b), (b, a)] {
+                if board.solved[cell] {
+                    continue;
+                }
+                // the rules read the digits in the order of the pair
+                let fits_pair = |vc: u32, vo: u32| match cell == a {
+                    true => fits(&rules, vc + 1, vo + 1),
+                    false => fits(&rules, vo + 1, vc + 1),
+                };
+                let mut allowed = 0;
+                for vc in (0..board.size as u32).filter(|v| values[cell] & (1 << v) != 0) {
+                    if (0..board.size as u32).any(|vo| values[other] & (1 << vo) != 0 && fits_pair(vc, vo)) {
+                        allowed |= 1 << vc;
+                    }
+                }
+                values[cell] &= allowed;
+            }
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let mut bad_cells = Vec::new();
+        for (a, b, rules) in self.rules(board) {
+            if !board.solved[a] || !board.solved[b] {
+                continue;
+            }
+            let (va, vb) =
+                (board.values[a].trailing_zeros() + 1, board.values[b].trailing_zeros() + 1);
+            if !fits(&rules, va, vb) {
+                bad_cells.push(a);
+                bad_cells.push(b);
+            }
+        }
+        bad_cells.sort_unstable();
+        bad_cells.dedup();
+        bad_cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let edges = Edges {
+            marks: vec![
+                (0, 1, EdgeKind::Black),
+                (2, 3, EdgeKind::V),
+                (4, 13, EdgeKind::X),
+                (19, 18, EdgeKind::Greater),
+                (27, 28, EdgeKind::Greater),
+            ],
+            negative: vec![],
+        };
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000001000;
+        values[4] = 0b100000000;
+        values[18] = 0b000000100;
+        values[27] = 0b000000100;
+        edges.prune(&board, &mut values);
+        // 4 next to a black dot has to be by a 2 or an 8
+        assert_eq!(values[1], 0b010000010);
+        assert_eq!(values[2], 0b000001111);
+        assert_eq!(values[13], 0b000000001);
+        assert_eq!((values[19], values[28]), (0b111111000, 0b000000011));
+
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000010000;
+        Edges::non_consecutive().prune(&board, &mut values);
+        assert_eq!((values[1], values[9], values[10]), (0b111010111, 0b111010111, 0b111111111));
+    }
+
+    #[test]
+    fn test_edges_conflicts() {
+        let board = Puzzle::raw_from_grid(&[&[1, 2, 4, 6][..], &[0; 77]].concat());
+        let edges = Edges {
+            marks: vec![(0, 1, EdgeKind::White), (1, 2, EdgeKind::Black)],
+            negative: vec![],
+        };
+        assert_eq!(edges.conflicts(&board), Vec::<usize>::new());
+
+        let edges = Edges {
+            marks: vec![(0, 1, EdgeKind::White)],
+            negative: vec![EdgeKind::White, EdgeKind::Black],
+        };
+        // 2 and 4 fit a black dot that isn't there
+        assert_eq!(edges.conflicts(&board), vec![1, 2]);
+        assert_eq!(Edges::non_consecutive().conflicts(&board), vec![0, 1]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::constraints::{distinct_bounds, distinct_sum_allowed, restrict, Constraint};
+use crate::{all_candidates, Candidates, Puzzle};
+
+/// The rule a line puts on the digits along it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineKind {
+    /// German whispers: neighbors on the line differ by at least half the digits (5 in a 9x9).
+    Whisper,
+    /// Renban: the digits are a set of consecutive numbers in any order.
+    Renban,
+    /// The line reads the same from either end.
+    Palindrome,
+    /// Each part of the line inside one box adds up to the same total.
+    RegionSum,
+}
+
+/// A line drawn through `cells` in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub kind: LineKind,
+    pub cells: Vec<usize>,
+}
+
+/// The smallest difference between neighbors on a whisper line.
+fn whisper_gap(size: usize) -> u32 {
+    (size as u32).div_ceil(2)
+}
+
+impl Line {
+    /// Split the line into the runs of cells that stay in one box.
+    fn segments(&self, board: &Puzzle) -> Vec<&[usize]> {
+        let region = |cell: usize| board.geometry().unit_in(2, cell);
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for i in 1..=self.cells.len() {
+            if i == self.cells.len() || region(self.cells[i]) != region(self.cells[i - 1]) {
+                segments.push(&self.cells[start..i]);
+                start = i;
+            }
+        }
+        segments
+    }
+
+    fn prune_whisper(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let gap = whisper_gap(board.size);
+        // the digits that are far enough from at least one of the candidates in `mask`
+        let far_from = |mask: Candidates| {
+            (0..board.size as u32)
+                .filter(|v| (0..board.size as u32).any(|w| mask & (1 << w) != 0 && v.abs_diff(w) >= gap))
+                .fold(0, |acc, v| acc | (1 << v))
+        };
+        let pairs: Vec<(usize, usize)> = self.cells.windows(2).map(|w| (w[0], w[1])).collect();
+        // going along the line and back carries each cell's limits to the far end
+        for (a, b) in pairs.iter().chain(pairs.iter().rev()) {
+            for (from, to) in [(*a, *b), (*b, *a)] {
+                if !board.solved[to] {
+                    values[to] &= far_from(values[from]);
+                }
+            }
+        }
+    }
+
+    fn prune_renban(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let len = self.cells.len();
+        if len > board.size {
+            restrict(board, values, &self.cells, &vec![0; len]);
+            return;
+        }
+        let mut allowed = 0;
+        for start in 0..=board.size - len {
+            let window = all_candidates(len) << start;
+            // every cell needs a digit in the window and every digit in it needs a cell
+            let union = self.cells.iter().fold(0, |acc, c| acc | (values[*c] & window));
+            if union == window && self.cells.iter().all(|c| values[*c] & window != 0) {
+                allowed |= window;
+            }
+        }
+        restrict(board, values, &self.cells, &vec![allowed; len]);
+    }
+
+    fn prune_palindrome(&self, board: &Puzzle, values: &mut [Candidates]) {
+        for (a, b) in self.cells.iter().zip(self.cells.iter().rev()) {
+            let both = values[*a] & values[*b];
+            restrict(board, values, &[*a, *b], &[both, both]);
+        }
+    }
+
+    fn prune_region_sum(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let segments = self.segments(board);
+        let Some(bounds) = segments
+            .iter()
+            .map(|s| distinct_bounds(s, values, 0))
+            .collect::<Option<Vec<(u32, u32)>>>()
+        else {
+            restrict(board, values, &self.cells, &vec![0; self.cells.len()]);
+            return;
+        };
+        let low = bounds.iter().map(|b| b.0).max().unwrap_or(0);
+        let high = bounds.iter().map(|b| b.1).min().unwrap_or(0);
+        let mut allowed = vec![0; self.cells.len()];
+        for total in low..=high {
+            let Some(fits) = segments
+                .iter()
+                .map(|s| distinct_sum_allowed(s, values, 0, total))
+                .collect::<Option<Vec<Vec<Candidates>>>>()
+            else {
+                continue;
+            };
+            for (allowed, mask) in allowed.iter_mut().zip(fits.concat()) {
+                *allowed |= mask;
+            }
+        }
+        restrict(board, values, &self.cells, &allowed);
+    }
+}
+
+impl Constraint for Line {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        match self.kind {
+            LineKind::Whisper => self.prune_whisper(board, values),
+            LineKind::Renban => self.prune_renban(board, values),
+            LineKind::Palindrome => self.prune_palindrome(board, values),
+            LineKind::RegionSum => self.prune_region_sum(board, values),
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let digit = |cell: &usize| board.values[*cell].trailing_zeros() + 1;
+        let solved: Vec<usize> = self.cells.iter().copied().filter(|c| board.solved[*c]).collect();
+        let mut bad_cells = Vec::new();
+        match self.kind {
+            LineKind::Whisper | LineKind::Palindrome => {
+                let pairs: Vec<(usize, usize)> = match self.kind {
+                    LineKind::Whisper => self.cells.windows(2).map(|w| (w[0], w[1])).collect(),
+                    _ => self.cells.iter().copied().zip(self.cells.iter().copied().rev()).collect(),
+                };
+                for (a, b) in pairs.into_iter().filter(|(a, b)| board.solved[*a] && board.solved[*b]) {
+                    let fine = match self.kind {
+                        LineKind::Whisper => digit(&a).abs_diff(digit(&b)) >= whisper_gap(board.size),
+                        _ => digit(&a) == digit(&b),
+                    };
+                    if !fine {
+                        bad_cells.extend([a, b]);
+                    }
+                }
+            }
+            LineKind::Renban => {
+                let digits: Vec<u32> = solved.iter().map(digit).collect();
+                let spread = digits.iter().max().zip(digits.iter().min()).map_or(0, |(h, l)| h - l);
+                let repeats = (1..digits.len()).any(|i| digits[..i].contains(&digits[i]));
+                if repeats || spread as usize >= self.cells.len() {
+                    bad_cells = solved;
+                }
+            }
+            LineKind::RegionSum => {
+                let full: Vec<&[usize]> =
+                    self.segments(board).into_iter().filter(|s| s.iter().all(|c| board.solved[*c])).collect();
+                let totals: Vec<u32> = full.iter().map(|s| s.iter().map(digit).sum()).collect();
+                if totals.windows(2).any(|w| w[0] != w[1]) {
+                    bad_cells = full.concat();
+                }
+            }
+        }
+        bad_cells.sort_unstable();
+        bad_cells.dedup();
+        bad_cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let whisper = Line { kind: LineKind::Whisper, cells: vec![0, 1, 2] };
+        let mut values = vec![0b111111111; 81];
+        values[1] = 0b000011000;
+        whisper.prune(&board, &mut values);
+        // a 5 in the middle has nothing 5 away from it and a 4 only has a 9
+        assert_eq!(&values[..3], &[0b100000000, 0b000001000, 0b100000000]);
+
+        let renban = Line { kind: LineKind::Renban, cells: vec![0, 1, 2] };
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000000010;
+        renban.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b10, 0b1111, 0b1111]);
+
+        let palindrome = Line { kind: LineKind::Palindrome, cells: vec![0, 1, 2] };
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000000110;
+        palindrome.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b110, 0b111111111, 0b110]);
+
+        // r1c3 alone in the first box has to match r1c4 + r1c5 in the second
+        let region_sum = Line { kind: LineKind::RegionSum, cells: vec![2, 3, 4] };
+        let mut values = vec![0b111111111; 81];
+        region_sum.prune(&board, &mut values);
+        assert_eq!(&values[2..5], &[0b111111100, 0b11111111, 0b11111111]);
+    }
+
+    #[test]
+    fn test_line_conflicts() {
+        let board = Puzzle::raw_from_grid(&[&[1, 6, 3, 2, 4, 5, 0, 0, 0][..], &[0; 72]].concat());
+        let line = |kind, cells| Line { kind, cells };
+        assert_eq!(line(LineKind::Whisper, vec![0, 1, 2]).conflicts(&board), vec![1, 2]);
+        assert_eq!(line(LineKind::Renban, vec![2, 3, 4]).conflicts(&board), Vec::<usize>::new());
+        assert_eq!(line(LineKind::Renban, vec![0, 2, 3]).conflicts(&board), Vec::<usize>::new());
+        assert_eq!(line(LineKind::Renban, vec![0, 1, 2]).conflicts(&board), vec![0, 1, 2]);
+        assert_eq!(line(LineKind::Palindrome, vec![2, 6, 3]).conflicts(&board), vec![2, 3]);
+        assert_eq!(line(LineKind::RegionSum, vec![1, 2, 4, 5]).conflicts(&board), Vec::<usize>::new());
+        assert_eq!(line(LineKind::RegionSum, vec![2, 3, 4]).conflicts(&board), vec![2, 3, 4]);
+    }
+}
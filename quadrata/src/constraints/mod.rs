@@ -0,0 +1,102 @@
+use std::any::Any;
+
+use crate::{Candidates, Puzzle};
+
+pub mod arrow;
+pub mod cage;
+pub mod edges;
+pub mod lines;
+pub mod movement;
+pub mod outside;
+pub mod parity;
+pub mod regions;
+pub mod thermo;
+
+/// A rule that the digits in a puzzle have to follow.
+///
+/// Rules that just stop some pairs of cells (peers) from sharing a digit only need to provide
+/// `peers`; pruning and conflicts then follow from it.
+pub trait Constraint: Any {
+    /// The cells that can't have the same digit as `cell` because of this constraint.
+    fn peers(&self, _board: &Puzzle, _cell: usize) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Remove candidates from `values` that can't be placed without breaking the constraint.
+    ///
+    /// Cells that `board` marks as solved hold their digit as a single bit in `values` and
+    /// shouldn't be changed.
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        for cell in (0..values.len()).filter(|i| board.solved[*i]) {
+            for peer in self.peers(board, cell) {
+                if !board.solved[peer] {
+                    values[peer] &= !values[cell];
+                }
+            }
+        }
+    }
+
+    /// The solved cells that break the constraint.
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        (0..board.values.len())
+            .filter(|i| board.solved[*i])
+            .filter(|i| {
+                self.peers(board, *i)
+                    .iter()
+                    .any(|p| board.solved[*p] && board.values[*p] == board.values[*i])
+            })
+            .collect()
+    }
+}
+
+/// The lowest and highest sums of distinct digits the cells could have, leaving out the
+/// digits in `exclude`.
+pub(crate) fn distinct_bounds(cells: &[usize], values: &[Candidates], exclude: Candidates) -> Option<(u32, u32)> {
+    let union = cells.iter().fold(0, |acc, c| acc | values[*c]) & !exclude;
+    let digits: Vec<u32> = (0..Candidates::BITS).filter(|v| union & (1 << v) != 0).map(|v| v + 1).collect();
+    if digits.len() < cells.len() || cells.iter().any(|c| values[*c] & !exclude == 0) {
+        return None;
+    }
+    let low = digits[..cells.len()].iter().sum();
+    let high = digits[digits.len() - cells.len()..].iter().sum();
+    Some((low, high))
+}
+
+/// The digits each of `cells` can have so that the cells add up to `sum` without repeating
+/// or using the digits in `exclude`, or `None` if they can't.
+pub(crate) fn distinct_sum_allowed(
+    cells: &[usize],
+    values: &[Candidates],
+    exclude: Candidates,
+    sum: u32,
+) -> Option<Vec<Candidates>> {
+    let (low, high) = distinct_bounds(cells, values, exclude)?;
+    if sum < low || sum > high {
+        return None;
+    }
+    let mut allowed = Vec::new();
+    for cell in cells {
+        let rest: Vec<usize> = cells.iter().copied().filter(|c| c != cell).collect();
+        let mask = (0..Candidates::BITS)
+            .filter(|v| values[*cell] & !exclude & (1 << v) != 0 && sum > *v)
+            .filter(|v| {
+                distinct_bounds(&rest, values, exclude | (1 << v))
+                    .is_some_and(|(low, high)| (low..=high).contains(&(sum - v - 1)))
+            })
+            .fold(0, |acc, v| acc | (1 << v));
+        if mask == 0 {
+            return None;
+        }
+        allowed.push(mask);
+    }
+    Some(allowed)
+}
+
+/// Remove the candidates that aren't in `allowed` from the unsolved cells.
+pub(crate) fn restrict(board: &Puzzle, values: &mut [Candidates], cells: &[usize], allowed: &[Candidates]) {
+    for (cell, allowed) in cells.iter().zip(allowed) {
+        if !board.solved[*cell] {
+            values[*cell] &= allowed;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::constraints::Constraint;
+use crate::Puzzle;
+
+const KNIGHT_MOVES: [(isize, isize); 8] =
+    [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+const KING_MOVES: [(isize, isize); 8] =
+    [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+/// The cells a chess piece moving by `moves` can reach from `cell` in one step.
+fn reachable(board: &Puzzle, cell: usize, moves: &[(isize, isize)]) -> Vec<usize> {
+    let mut cells: Vec<usize> =
+        moves.iter().filter_map(|step| board.geometry().offset(cell, *step)).collect();
+    // a small torus can bring several moves (or the cell itself) to the same place
+    cells.retain(|c| *c != cell);
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+}
+
+/// Cells a chess knight's move apart can't have the same digit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AntiKnight;
+
+impl Constraint for AntiKnight {
+    fn peers(&self, board: &Puzzle, cell: usize) -> Vec<usize> {
+        reachable(board, cell, &KNIGHT_MOVES)
+    }
+}
+
+/// Cells a chess king's move apart (including diagonally) can't have the same digit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AntiKing;
+
+impl Constraint for AntiKing {
+    fn peers(&self, board: &Puzzle, cell: usize) -> Vec<usize> {
+        reachable(board, cell, &KING_MOVES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::solutions;
+
+    #[test]
+    fn test_anti_knight() {
+        let board = Puzzle::raw_from_grid(&[&[1][..], &[0; 80]].concat());
+        assert_eq!(AntiKnight.peers(&board, 0), vec![11, 19]);
+        assert_eq!(AntiKnight.peers(&board, 40).len(), 8);
+
+        let mut values = vec![0b111111111; 81];
+        values[0] = 1;
+        AntiKnight.prune(&board, &mut values);
+        assert_eq!((values[11], values[19], values[20]), (0b111111110, 0b111111110, 0b111111111));
+
+        let board = Puzzle::raw_from_grid(&[&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1][..], &[0; 69]].concat());
+        assert_eq!(AntiKnight.conflicts(&board), vec![0, 11]);
+        assert_eq!(AntiKing.conflicts(&board), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_anti_king() {
+        let board = Puzzle::raw_from_grid(&[&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1][..], &[0; 70]].concat());
+        assert_eq!(AntiKing.peers(&board, 0), vec![1, 9, 10]);
+        assert_eq!(AntiKing.conflicts(&board), vec![0, 10]);
+
+        let mut board = Puzzle::raw_from_grid(&[0; 81]);
+        board.add_anti_king();
+        let solution = solutions(&board, 1).unwrap().remove(0);
+        assert_eq!(AntiKing.conflicts(&Puzzle::raw_from_grid(&solution)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_torus() {
+        let mut board = Puzzle::raw_from_grid(&[0; 81]);
+        board.set_torus(true);
+        assert_eq!(AntiKnight.peers(&board, 0), vec![11, 16, 19, 26, 64, 71, 74, 79]);
+        assert_eq!(AntiKing.peers(&board, 0), vec![1, 8, 9, 10, 17, 72, 73, 80]);
+
+        // the board's own peers pick up the wrapped moves once the rule is added
+        assert!(!board.peers(0).contains(&64));
+        board.add_anti_knight();
+        assert!(board.peers(0).contains(&64));
+    }
+}
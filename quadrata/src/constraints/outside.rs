@@ -0,0 +1,242 @@
+use crate::constraints::{distinct_sum_allowed, restrict, Constraint};
+use crate::{Candidates, Puzzle};
+
+/// A sandwich clue: the digits between the 1 and the highest digit in a row or column add up
+/// to `sum`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sandwich {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+/// An X-sum clue: the first digit read from the clue says how many cells (including itself)
+/// add up to `sum`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XSum {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+/// A little killer clue: the digits along a diagonal add up to `sum` (and may repeat).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LittleKiller {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+/// The cells a clue outside the grid looks at: starting from the 0-based `(row, col)` of the
+/// clue (so -1 or the width or height on the outside) and moving by `step` until leaving the
+/// grid.
+pub(crate) fn ray(board: &Puzzle, (row, col): (isize, isize), step: (isize, isize)) -> Vec<usize> {
+    let (mut row, mut col) = (row + step.0, col + step.1);
+    let mut cells = Vec::new();
+    // on a torus the ray would go around forever so it stops once it gets back
+    while let Some(cell) = board.cell_at(row, col).filter(|c| !cells.contains(c)) {
+        cells.push(cell);
+        (row, col) = (row + step.0, col + step.1);
+    }
+    cells
+}
+
+/// The solved cells if they already break adding up to `sum`, given that every open cell
+/// needs at least `least`.
+fn sum_conflicts(board: &Puzzle, cells: &[usize], sum: u32, least: u32) -> Vec<usize> {
+    let solved: Vec<usize> = cells.iter().copied().filter(|i| board.solved[*i]).collect();
+    let total: u32 = solved.iter().map(|i| board.values[*i].trailing_zeros() + 1).sum();
+    let n_open = (cells.len() - solved.len()) as u32;
+    if (n_open == 0 && total != sum) || total + least * n_open > sum {
+        solved
+    } else {
+        Vec::new()
+    }
+}
+
+/// The digit in a solved cell.
+fn digit(board: &Puzzle, cell: usize) -> Option<u32> {
+    board.solved[cell].then(|| board.values[cell].trailing_zeros() + 1)
+}
+
+impl Constraint for Sandwich {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let cells = &self.cells;
+        let (one, top) = (1, 1 << (board.size - 1));
+        let mut allowed = vec![0; cells.len()];
+        for i in (0..cells.len()).filter(|i| values[cells[*i]] & one != 0) {
+            for j in (0..cells.len()).filter(|j| *j != i && values[cells[*j]] & top != 0) {
+                let (a, b) = (i.min(j), i.max(j));
+                // only the two ends can hold a 1 or the highest digit
+                let outside = (0..cells.len()).filter(|k| *k < a || *k > b);
+                if outside.clone().any(|k| values[cells[k]] & !(one | top) == 0) {
+                    continue;
+                }
+                let between = &cells[a + 1..b];
+                let Some(between_allowed) = distinct_sum_allowed(between, values, one | top, self.sum)
+                else {
+                    continue;
+                };
+                allowed[i] |= one;
+                allowed[j] |= top;
+                for (k, mask) in (a + 1..b).zip(between_allowed) {
+                    allowed[k] |= mask;
+                }
+                for k in outside {
+                    allowed[k] |= values[cells[k]] & !(one | top);
+                }
+            }
+        }
+        restrict(board, values, cells, &allowed);
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let cells = &self.cells;
+        let find = |val| cells.iter().position(|c| digit(board, *c) == Some(val));
+        let (Some(i), Some(j)) = (find(1), find(board.size as u32)) else {
+            return Vec::new();
+        };
+        let (a, b) = (i.min(j), i.max(j));
+        // every digit in between is at least a 2
+        let mut bad_cells = sum_conflicts(board, &cells[a + 1..b], self.sum, 2);
+        if !bad_cells.is_empty() || (a + 1 == b && self.sum != 0) {
+            bad_cells.extend([cells[a], cells[b]]);
+        }
+        bad_cells.sort_unstable();
+        bad_cells
+    }
+}
+
+impl Constraint for XSum {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let cells = &self.cells;
+        let Some(first) = cells.first() else {
+            return;
+        };
+        let mut allowed = vec![0; cells.len()];
+        for count in (1..=cells.len().min(board.size)).filter(|n| values[*first] & (1 << (n - 1)) != 0) {
+            let bit = 1 << (count - 1);
+            let Some(rest) = self.sum.checked_sub(count as u32) else {
+                continue;
+            };
+            let Some(counted) = distinct_sum_allowed(&cells[1..count], values, bit, rest) else {
+                continue;
+            };
+            allowed[0] |= bit;
+            for (k, mask) in (1..count).zip(counted) {
+                allowed[k] |= mask;
+            }
+            for k in count..cells.len() {
+                allowed[k] |= values[cells[k]];
+            }
+        }
+        restrict(board, values, cells, &allowed);
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let Some(count) = self.cells.first().and_then(|c| digit(board, *c)) else {
+            return Vec::new();
+        };
+        match self.cells.get(..count as usize) {
+            Some(counted) => sum_conflicts(board, counted, self.sum, 1),
+            None => vec![self.cells[0]],
+        }
+    }
+}
+
+impl Constraint for LittleKiller {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        if self.cells.iter().any(|c| values[*c] == 0) {
+            return;
+        }
+        let lowest = |c: &usize| values[*c].trailing_zeros() + 1;
+        let highest = |c: &usize| Candidates::BITS - values[*c].leading_zeros();
+        let low: u32 = self.cells.iter().map(lowest).sum();
+        let high: u32 = self.cells.iter().map(highest).sum();
+        let allowed: Vec<Candidates> = self
+            .cells
+            .iter()
+            .map(|c| {
+                let (rest_low, rest_high) = (low - lowest(c), high - highest(c));
+                (0..Candidates::BITS)
+                    .filter(|v| (rest_low + v + 1..=rest_high + v + 1).contains(&self.sum))
+                    .fold(0, |acc, v| acc | (1 << v))
+            })
+            .collect();
+        restrict(board, values, &self.cells, &allowed);
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        sum_conflicts(board, &self.cells, self.sum, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ray() {
+        let board = Puzzle::raw_from_grid(&[0; 16]);
+        assert_eq!(ray(&board, (-1, 1), (1, 0)), vec![1, 5, 9, 13]);
+        assert_eq!(ray(&board, (2, 4), (0, -1)), vec![11, 10, 9, 8]);
+        assert_eq!(ray(&board, (-1, -1), (1, 1)), vec![0, 5, 10, 15]);
+        assert_eq!(ray(&board, (-1, 0), (1, 1)), vec![1, 6, 11]);
+        assert_eq!(ray(&board, (-1, 4), (1, -1)), vec![3, 6, 9, 12]);
+    }
+
+    #[test]
+    fn test_sandwich_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000000001;
+        values[8] = 0b100000000;
+        Sandwich { cells: (0..9).collect(), sum: 35 }.prune(&board, &mut values);
+        assert_eq!(values[1], 0b011111110);
+
+        // a sum of 0 means the 1 and 9 are next to each other
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b000000001;
+        Sandwich { cells: (0..9).collect(), sum: 0 }.prune(&board, &mut values);
+        assert_eq!(values[1], 0b100000000);
+        assert_eq!(values[2], 0b011111110);
+
+        let mut values = vec![0b111111111; 81];
+        Sandwich { cells: (0..9).collect(), sum: 35 }.prune(&board, &mut values);
+        // 35 needs all of 2 to 8 so the ends are the only places for a 1 or 9
+        assert_eq!((values[0], values[4], values[8]), (0b100000001, 0b011111110, 0b100000001));
+    }
+
+    #[test]
+    fn test_x_sum_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let mut values = vec![0b111111111; 81];
+        XSum { cells: (0..9).collect(), sum: 6 }.prune(&board, &mut values);
+        // 1 alone is too small, 2 + 4 or 3 + {1, 2} and more cells is too big
+        assert_eq!(&values[..4], &[0b110, 0b1011, 0b111111111, 0b111111111]);
+
+        let mut values = vec![0b111111111; 81];
+        values[0] = 0b100;
+        XSum { cells: (0..9).collect(), sum: 6 }.prune(&board, &mut values);
+        assert_eq!(&values[..4], &[0b100, 0b11, 0b11, 0b111111111]);
+    }
+
+    #[test]
+    fn test_little_killer_prune() {
+        let board = Puzzle::raw_from_grid(&[0; 81]);
+        let mut values = vec![0b111111111; 81];
+        values[10] = 0b1;
+        LittleKiller { cells: vec![0, 10, 20], sum: 5 }.prune(&board, &mut values);
+        assert_eq!((values[0], values[10], values[20]), (0b111, 0b1, 0b111));
+    }
+
+    #[test]
+    fn test_outside_conflicts() {
+        let board = Puzzle::raw_from_grid(&[&[3, 1, 2, 4][..], &[0; 12]].concat());
+        let row: Vec<usize> = (0..4).collect();
+        assert_eq!(Sandwich { cells: row.clone(), sum: 2 }.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(Sandwich { cells: row.clone(), sum: 3 }.conflicts(&board), vec![1, 2, 3]);
+        assert_eq!(XSum { cells: row.clone(), sum: 6 }.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(XSum { cells: row.clone(), sum: 7 }.conflicts(&board), vec![0, 1, 2]);
+        assert_eq!(XSum { cells: vec![0, 4], sum: 3 }.conflicts(&board), vec![0]);
+        assert_eq!(LittleKiller { cells: vec![0, 5], sum: 3 }.conflicts(&board), vec![0]);
+        assert_eq!(LittleKiller { cells: vec![0, 5], sum: 5 }.conflicts(&board), Vec::<usize>::new());
+    }
+}
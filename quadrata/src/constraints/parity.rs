@@ -0,0 +1,53 @@
+use crate::constraints::Constraint;
+use crate::{Candidates, Puzzle};
+
+/// Shaded cells that can only hold even (or only odd) digits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parity {
+    pub cells: Vec<usize>,
+    pub even: bool,
+}
+
+impl Parity {
+    /// The candidates with the right parity (bit 0 is the digit 1, so odd digits are on the
+    /// even bits).
+    fn mask(&self) -> Candidates {
+        match self.even {
+            true => 0xAAAA_AAAA,
+            false => 0x5555_5555,
+        }
+    }
+}
+
+impl Constraint for Parity {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        for cell in self.cells.iter().filter(|c| !board.solved[**c]) {
+            values[*cell] &= self.mask();
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        self.cells
+            .iter()
+            .copied()
+            .filter(|c| board.solved[*c] && board.values[*c] & self.mask() == 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parity() {
+        let board = Puzzle::raw_from_grid(&[&[1, 2, 0][..], &[0; 78]].concat());
+        let mut values = vec![0b111111111; 81];
+        Parity { cells: vec![2, 3], even: true }.prune(&board, &mut values);
+        Parity { cells: vec![4], even: false }.prune(&board, &mut values);
+        assert_eq!(&values[..5], &[0b111111111, 0b111111111, 0b010101010, 0b010101010, 0b101010101]);
+
+        assert_eq!(Parity { cells: vec![0, 1, 2], even: true }.conflicts(&board), vec![0]);
+        assert_eq!(Parity { cells: vec![0, 1, 2], even: false }.conflicts(&board), vec![1]);
+    }
+}
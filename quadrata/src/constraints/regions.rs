@@ -0,0 +1,94 @@
+use crate::constraints::Constraint;
+use crate::solver::get_counts;
+use crate::{Candidates, Puzzle};
+
+/// Every digit appears at most once in each region of `Puzzle::boxes` (the rows, columns and
+/// boxes of a plain sudoku plus any extra region sets).
+#[derive(Clone, Copy, Debug)]
+pub struct Regions;
+
+impl Constraint for Regions {
+    fn peers(&self, board: &Puzzle, cell: usize) -> Vec<usize> {
+        board.geometry().peers(cell).to_vec()
+    }
+
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let geometry = board.geometry();
+        let mut seen: Vec<Candidates> = vec![0; geometry.n_units()];
+        for i in (0..values.len()).filter(|i| board.solved[*i]) {
+            for unit in geometry.units_of(i) {
+                seen[*unit] |= values[i];
+            }
+        }
+        for i in (0..values.len()).filter(|i| !board.solved[*i]) {
+            for unit in geometry.units_of(i) {
+                values[i] &= !seen[*unit];
+            }
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let counts = get_counts(board, true);
+        (0..board.values.len())
+            .filter(|i| board.solved[*i])
+            .filter(|i| {
+                let v = board.values[*i].trailing_zeros() as usize;
+                board.geometry().units_of(*i).iter().any(|unit| counts[*unit][v] > 1)
+            })
+            .collect()
+    }
+}
+
+/// Cells that can't repeat a digit but don't have to hold all of them, like the extra regions
+/// f-puzzles draws. Unlike the region sets in `Puzzle::boxes` the strategies don't take it to
+/// have every digit in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtraRegion {
+    pub cells: Vec<usize>,
+}
+
+impl Constraint for ExtraRegion {
+    fn peers(&self, _board: &Puzzle, cell: usize) -> Vec<usize> {
+        if !self.cells.contains(&cell) {
+            return Vec::new();
+        }
+        self.cells.iter().copied().filter(|c| *c != cell).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let mut board = Puzzle::raw_from_grid(&vec![1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut values = vec![15; 16];
+        values[0] = 1;
+        values[7] = 1;
+        Regions.prune(&board, &mut values);
+        assert_eq!(values, vec![1, 14, 14, 14, 14, 14, 14, 1, 14, 15, 15, 14, 14, 15, 15, 14]);
+        assert_eq!(Regions.conflicts(&board), Vec::<usize>::new());
+        assert_eq!(Regions.peers(&board, 0), vec![1, 2, 3, 4, 5, 8, 12]);
+
+        board.values[3] = 1;
+        board.solved[3] = true;
+        assert_eq!(Regions.conflicts(&board), vec![0, 3, 7]);
+    }
+
+    #[test]
+    fn test_extra_region() {
+        let mut board = Puzzle::raw_from_grid(&vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let region = ExtraRegion { cells: vec![0, 10, 15] };
+        let mut values = vec![15; 16];
+        values[0] = 1;
+        region.prune(&board, &mut values);
+        assert_eq!(values[10], 14);
+        assert_eq!(values[15], 14);
+        assert_eq!(region.peers(&board, 5), Vec::<usize>::new());
+
+        board.values[15] = 1;
+        board.solved[15] = true;
+        assert_eq!(region.conflicts(&board), vec![0, 15]);
+    }
+}
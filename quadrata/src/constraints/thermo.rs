@@ -0,0 +1,86 @@
+use crate::constraints::Constraint;
+use crate::{all_candidates, Candidates, Puzzle};
+
+/// A thermometer: the digits strictly increase along `cells`, starting from the bulb.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Thermo {
+    pub cells: Vec<usize>,
+}
+
+impl Constraint for Thermo {
+    fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+        let all = all_candidates(board.size);
+        // each cell has to be above the lowest digit the cell before it can be
+        let mut low = 0;
+        for cell in &self.cells {
+            if !board.solved[*cell] {
+                values[*cell] &= all & !all_candidates(low as usize);
+            }
+            if values[*cell] == 0 {
+                return;
+            }
+            low = values[*cell].trailing_zeros() + 1;
+        }
+        // and below the highest digit the cell after it can be
+        let mut high = board.size as u32 + 1;
+        for cell in self.cells.iter().rev() {
+            if !board.solved[*cell] {
+                values[*cell] &= all_candidates(high as usize - 1);
+            }
+            if values[*cell] == 0 {
+                return;
+            }
+            high = Candidates::BITS - values[*cell].leading_zeros();
+        }
+    }
+
+    fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+        let mut bad_cells = Vec::new();
+        for (i, a) in self.cells.iter().enumerate() {
+            for (j, b) in self.cells.iter().enumerate().skip(i + 1) {
+                if !board.solved[*a] || !board.solved[*b] {
+                    continue;
+                }
+                let (va, vb) = (board.values[*a].trailing_zeros(), board.values[*b].trailing_zeros());
+                // the cells in between need room for their own increasing digits
+                if vb < va + (j - i) as u32 {
+                    bad_cells.push(*a);
+                    bad_cells.push(*b);
+                }
+            }
+        }
+        bad_cells.sort_unstable();
+        bad_cells.dedup();
+        bad_cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thermo() {
+        let mut board = Puzzle::raw_from_grid(&[0; 81]);
+        let mut values = vec![0b111111111; 81];
+        let thermo = Thermo { cells: vec![0, 1, 2] };
+        thermo.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b001111111, 0b011111110, 0b111111100]);
+
+        board.values[1] = 1 << 4;
+        board.solved[1] = true;
+        let mut values = vec![0b111111111; 81];
+        values[1] = 1 << 4;
+        thermo.prune(&board, &mut values);
+        assert_eq!(&values[..3], &[0b000001111, 0b000010000, 0b111100000]);
+        assert_eq!(thermo.conflicts(&board), Vec::<usize>::new());
+
+        board.values[2] = 1 << 4;
+        board.solved[2] = true;
+        assert_eq!(thermo.conflicts(&board), vec![1, 2]);
+        board.values[2] = 1 << 5;
+        board.values[0] = 1 << 5;
+        board.solved[0] = true;
+        assert_eq!(thermo.conflicts(&board), vec![0, 1, 2]);
+    }
+}
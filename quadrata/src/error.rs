@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Everything that can go wrong when loading or solving a puzzle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuadrataError {
+    /// The puzzle text didn't have the expected number of cells.
+    InvalidLength { expected: usize, found: usize },
+    /// The puzzle text had a character that isn't a digit or a blank.
+    InvalidCharacter { pos: usize, ch: char },
+    /// A region doesn't have one cell for every digit.
+    InvalidRegion { region: usize, cells: usize },
+    /// A line describing a variant constraint couldn't be read.
+    InvalidConstraint { line: usize, text: String },
+    /// Puzzle data from another app couldn't be decoded, with the reason why.
+    InvalidFormat(String),
+    /// The givens contradict each other.
+    NoSolution,
+    /// The givens don't pin down a single solution, with the cells that differ between two of
+    /// the solutions (see `Puzzle::ambiguous_cells` and `Puzzle::suggest_givens` for more).
+    MultipleSolutions { cells: Vec<usize> },
+    /// The solver gave up before finding out how many solutions the board has.
+    SearchLimit,
+    /// The solver doesn't know how to handle a board of this size.
+    UnsupportedSize(usize),
+    /// The solver doesn't know how to handle the board's constraints.
+    UnsupportedConstraints,
+}
+
+impl QuadrataError {
+    /// A stable, machine-readable name for the error (e.g. for localizing messages).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QuadrataError::InvalidLength { .. } => "invalid_length",
+            QuadrataError::InvalidCharacter { .. } => "invalid_character",
+            QuadrataError::InvalidRegion { .. } => "invalid_region",
+            QuadrataError::InvalidConstraint { .. } => "invalid_constraint",
+            QuadrataError::InvalidFormat(_) => "invalid_format",
+            QuadrataError::NoSolution => "no_solution",
+            QuadrataError::MultipleSolutions { .. } => "multiple_solutions",
+            QuadrataError::SearchLimit => "search_limit",
+            QuadrataError::UnsupportedSize(_) => "unsupported_size",
+            QuadrataError::UnsupportedConstraints => "unsupported_constraints",
+        }
+    }
+}
+
+impl fmt::Display for QuadrataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuadrataError::InvalidLength { expected, found } => {
+                write!(f, "text must be {} characters long (got {})", expected, found)
+            }
+            QuadrataError::InvalidCharacter { pos, ch } => {
+                write!(f, "character {} can not be present (at position {})", ch, pos)
+            }
+            QuadrataError::InvalidRegion { region, cells } => {
+                write!(f, "region {} has {} cells", region, cells)
+            }
+            QuadrataError::InvalidConstraint { line, text } => {
+                write!(f, "constraint \"{}\" on line {} can not be read", text, line)
+            }
+            QuadrataError::InvalidFormat(reason) => write!(f, "puzzle data can not be read: {}", reason),
+            QuadrataError::NoSolution => write!(f, "Board has no valid solution"),
+            QuadrataError::MultipleSolutions { cells } => {
+                write!(f, "Board has multiple valid solutions (differing in {} cells)", cells.len())
+            }
+            QuadrataError::SearchLimit => write!(f, "Board took too many guesses to solve"),
+            QuadrataError::UnsupportedSize(size) => {
+                write!(f, "Board of size {} can not be solved", size)
+            }
+            QuadrataError::UnsupportedConstraints => {
+                write!(f, "Only traditional sudoku can be solved")
+            }
+        }
+    }
+}
+
+impl Error for QuadrataError {}
+
+/// Errors cross into JS as an `Error` with the message and a `kind` property for the type.
+#[cfg(feature = "wasm")]
+impl From<QuadrataError> for JsValue {
+    fn from(err: QuadrataError) -> JsValue {
+        let js_err = js_sys::Error::new(&err.to_string());
+        js_err.set_name("QuadrataError");
+        // setting a property on a fresh object can't fail
+        let _ = js_sys::Reflect::set(&js_err, &"kind".into(), &err.kind().into());
+        js_err.into()
+    }
+}
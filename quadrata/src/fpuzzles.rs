@@ -0,0 +1,324 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use serde_json::Value;
+
+use crate::constraints::outside::ray;
+use crate::io::{facing, parse_cell, parse_outside};
+use crate::lzstring::decompress_from_link;
+use crate::solver::solve;
+use crate::{EdgeKind, Edges, Puzzle, QuadrataError};
+
+/// Entries of an f-puzzles puzzle that don't change its solution: the grid itself (read on
+/// its own), titles and rules, the solution and cosmetic text, lines and shapes. Any other key
+/// the solver doesn't know (e.g. minimum, clone or between lines) is an error if it's used.
+const COSMETIC: &[&str] = &[
+    "size",
+    "grid",
+    "title",
+    "author",
+    "ruleset",
+    "solution",
+    "text",
+    "line",
+    "rectangle",
+    "circle",
+    "cage",
+    "disabledlogic",
+    "truecandidatesoptions",
+];
+
+fn invalid(reason: &str) -> QuadrataError {
+    QuadrataError::InvalidFormat(reason.to_string())
+}
+
+/// Read a puzzle made in f-puzzles, from a link to it, the compressed data after `load=` in
+/// the link or the JSON it holds.
+///
+/// The grid's givens and regions come across along with killer cages, extra regions,
+/// thermometers, arrows, whispers, renban, palindrome and region sum lines, the diagonals,
+/// anti-knight, anti-king, disjoint groups, Kropki dots, XV sums (and their negative
+/// constraints), odd and even cells, sandwich sums, X-sums and little killers. Titles, rules
+/// and purely cosmetic shapes are skipped, and anything else that's switched on or drawn is an
+/// `UnsupportedConstraints` error.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn from_fpuzzles(text: &str) -> Result<Puzzle, QuadrataError> {
+    let data: Value =
+        serde_json::from_str(&fpuzzles_json(text)?).map_err(|e| QuadrataError::InvalidFormat(e.to_string()))?;
+    let size = data["size"].as_u64().ok_or_else(|| invalid("missing size"))? as usize;
+    if !(1..=32).contains(&size) {
+        return Err(QuadrataError::UnsupportedSize(size));
+    }
+    let rows = data["grid"].as_array().filter(|r| r.len() == size).ok_or_else(|| invalid("bad grid"))?;
+    let mut givens = Vec::new();
+    let mut regions = Vec::new();
+    for row in rows {
+        let row = row.as_array().filter(|r| r.len() == size).ok_or_else(|| invalid("bad grid"))?;
+        for cell in row {
+            // values without `given` are the solver's own entries saved along with the puzzle
+            givens.push(match (cell["given"].as_bool(), number(&cell["value"])) {
+                (Some(true), Some(value)) if (1..=size).contains(&(value as usize)) => value as u8,
+                (Some(true), _) => return Err(invalid("bad given")),
+                _ => 0,
+            });
+            regions.push(cell["region"].as_u64().map(|r| r as usize));
+        }
+    }
+    let mut puzzle = Puzzle::raw_from_grid(&givens);
+    if regions.iter().any(Option::is_some) {
+        // cells without a region of their own keep their usual box
+        let regions = regions.iter().zip(&puzzle.boxes[2]).map(|(r, b)| r.map_or(*b, |r| r + 1));
+        puzzle.set_regions(regions.collect())?;
+    }
+
+    // the edge clues all go into one constraint since the negative clues depend on every mark
+    let mut edges = Edges::default();
+    let entries = data.as_object().ok_or_else(|| invalid("not a puzzle"))?;
+    for (key, value) in entries {
+        let items = value.as_array().map_or(&[][..], |a| &a[..]);
+        let on = value.as_bool() == Some(true);
+        let bad = || QuadrataError::InvalidFormat(format!("bad {}", key));
+        match key.as_str() {
+            "killercage" => {
+                for item in items {
+                    let sum = number(&item["value"]).unwrap_or(0);
+                    puzzle.add_cage(cells(&item["cells"], &puzzle).ok_or_else(bad)?, sum);
+                }
+            }
+            "extraregion" => {
+                for item in items {
+                    puzzle.add_extra_region(cells(&item["cells"], &puzzle).ok_or_else(bad)?);
+                }
+            }
+            "thermometer" | "palindrome" | "renban" | "whispers" | "regionsumline" => {
+                for item in items {
+                    for line in lines(item, &puzzle).ok_or_else(bad)? {
+                        match key.as_str() {
+                            "thermometer" => puzzle.add_thermo(line),
+                            "palindrome" => puzzle.add_palindrome(line),
+                            "renban" => puzzle.add_renban(line),
+                            "whispers" => puzzle.add_whisper(line),
+                            _ => puzzle.add_region_sum_line(line),
+                        }
+                    }
+                }
+            }
+            "arrow" => {
+                for item in items {
+                    let circle = cells(&item["cells"], &puzzle).ok_or_else(bad)?;
+                    // the lines start inside the circle
+                    for line in lines(item, &puzzle).ok_or_else(bad)? {
+                        let path = line.into_iter().filter(|c| !circle.contains(c)).collect();
+                        puzzle.add_arrow(circle.clone(), path);
+                    }
+                }
+            }
+            "diagonal+" if on => puzzle.add_diagonal(false),
+            "diagonal-" if on => puzzle.add_diagonal(true),
+            "antiknight" if on => puzzle.add_anti_knight(),
+            "antiking" if on => puzzle.add_anti_king(),
+            "disjointgroups" if on => puzzle.add_disjoint_groups()?,
+            "nonconsecutive" if on => edges.negative.push(EdgeKind::White),
+            "difference" | "ratio" | "xv" => {
+                for item in items {
+                    let kind = match (key.as_str(), &item["value"]) {
+                        ("difference", Value::Null) | ("ratio", Value::Null) => None,
+                        (_, value) => Some(value.as_str().map(str::to_string).unwrap_or(value.to_string())),
+                    };
+                    let kind = match (key.as_str(), kind.as_deref()) {
+                        ("difference", None | Some("1")) => EdgeKind::White,
+                        ("ratio", None | Some("2")) => EdgeKind::Black,
+                        ("xv", Some("X" | "x")) => EdgeKind::X,
+                        ("xv", Some("V" | "v")) => EdgeKind::V,
+                        ("xv", _) => return Err(bad()),
+                        _ => return Err(QuadrataError::UnsupportedConstraints),
+                    };
+                    match cells(&item["cells"], &puzzle).ok_or_else(bad)?[..] {
+                        [a, b] if puzzle.geometry().touching(a, b) => edges.marks.push((a, b, kind)),
+                        _ => return Err(bad()),
+                    }
+                }
+            }
+            "negative" => {
+                for kind in items {
+                    match kind.as_str() {
+                        Some("ratio") => edges.negative.push(EdgeKind::Black),
+                        Some("difference") => edges.negative.push(EdgeKind::White),
+                        Some("xv") => edges.negative.extend([EdgeKind::X, EdgeKind::V]),
+                        _ => return Err(QuadrataError::UnsupportedConstraints),
+                    }
+                }
+            }
+            "odd" | "even" => {
+                let shaded: Option<Vec<usize>> =
+                    items.iter().map(|item| item["cell"].as_str().and_then(|c| parse_cell(c, &puzzle))).collect();
+                let shaded = shaded.ok_or_else(bad)?;
+                match key.as_str() {
+                    _ if shaded.is_empty() => {}
+                    "odd" => puzzle.add_odd(shaded),
+                    _ => puzzle.add_even(shaded),
+                }
+            }
+            "sandwichsum" | "xsum" | "littlekillersum" => {
+                for item in items {
+                    let clue = item["cell"].as_str().and_then(|c| parse_outside(c, &puzzle)).ok_or_else(bad)?;
+                    // clues without a number are just decoration
+                    let Some(sum) = number(&item["value"]) else {
+                        continue;
+                    };
+                    let step = match (key.as_str(), item["direction"].as_str()) {
+                        ("littlekillersum", Some("DR")) => (1, 1),
+                        ("littlekillersum", Some("DL")) => (1, -1),
+                        ("littlekillersum", Some("UR")) => (-1, 1),
+                        ("littlekillersum", Some("UL")) => (-1, -1),
+                        ("littlekillersum", _) => return Err(bad()),
+                        _ => facing(clue, &puzzle).ok_or_else(bad)?,
+                    };
+                    let cells = ray(&puzzle, clue, step);
+                    match key.as_str() {
+                        _ if cells.is_empty() => return Err(bad()),
+                        "sandwichsum" => puzzle.add_sandwich(cells, sum),
+                        "xsum" => puzzle.add_x_sum(cells, sum),
+                        _ => puzzle.add_little_killer(cells, sum),
+                    }
+                }
+            }
+            key if COSMETIC.contains(&key) => {}
+            // rules that are switched off or have nothing drawn
+            _ if unused(value) => {}
+            _ => return Err(QuadrataError::UnsupportedConstraints),
+        }
+    }
+    edges.negative.sort_by_key(|k| *k as u8);
+    edges.negative.dedup();
+    if edges != Edges::default() {
+        puzzle.add_constraint(edges);
+    }
+    puzzle.truths = solve(&puzzle)?;
+    Ok(puzzle)
+}
+
+/// The JSON of a puzzle from a link, the compressed data in one or the JSON itself.
+fn fpuzzles_json(text: &str) -> Result<String, QuadrataError> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        return Ok(text.to_string());
+    }
+    let data = text.rsplit_once("load=").map_or(text, |(_, data)| data);
+    let data = data.split(['&', '#']).next().unwrap_or(data);
+    decompress_from_link(data).ok_or_else(|| invalid("not compressed puzzle data"))
+}
+
+/// A number that f-puzzles may have saved as either a number or a string.
+fn number(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Whether an entry is switched off or empty.
+fn unused(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        Value::Bool(true) | Value::Number(_) => false,
+    }
+}
+
+/// A list of cells written like `R1C2`.
+fn cells(value: &Value, puzzle: &Puzzle) -> Option<Vec<usize>> {
+    value.as_array()?.iter().map(|c| c.as_str().and_then(|c| parse_cell(c, puzzle))).collect()
+}
+
+/// The `lines` of a line constraint, each a list of cells.
+fn lines(item: &Value, puzzle: &Puzzle) -> Option<Vec<Vec<usize>>> {
+    item["lines"].as_array()?.iter().map(|line| cells(line, puzzle)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arrow, Cage, ExtraRegion, LittleKiller, Thermo};
+
+    /// The JSON for a 4x4 grid with `givens` (0 for blanks) and more keys appended.
+    fn json(givens: &str, extra: &str) -> String {
+        let cells: Vec<String> = givens
+            .chars()
+            .map(|c| match c {
+                '0' => "{}".to_string(),
+                _ => format!("{{\"value\":{},\"given\":true}}", c),
+            })
+            .collect();
+        let rows: Vec<String> = cells.chunks(4).map(|r| format!("[{}]", r.join(","))).collect();
+        format!("{{\"size\":4,\"grid\":[{}]{}}}", rows.join(","), extra)
+    }
+
+    #[test]
+    fn test_from_fpuzzles_link() {
+        // {"size":4,"grid":[...],"thermometer":[...]} for the io thermo example
+        let data = "N4IgzglgXgpiBcAWANCA5gJwgEwQbT2AF9ljSTQA3AQwBsBXOJVNCSmAOwQBcNGiAuskIUKIGgyYBGFm048+MUUJHk1xFWS3KhIbgAsYGALYB7YzG5H8oWhA4ww+PCABKUgMJSQqdx4BMPm6eAMwgAgJidg5O8ARuiB6IQa4hSeHCbmnevok5CQHhkZFAA==";
+        let expected = vec![1, 2, 3, 4, 4, 3, 1, 2, 2, 1, 4, 3, 3, 4, 2, 1];
+        let link = format!("https://www.f-puzzles.com/?load={}", data);
+        assert_eq!(from_fpuzzles(&link).unwrap().solution(), expected);
+        assert_eq!(from_fpuzzles(data).unwrap().solution(), expected);
+        assert_eq!(from_fpuzzles(&link.replace('=', "%3D").replacen("load%3D", "load=", 1)).unwrap().solution(), expected);
+        assert_eq!(from_fpuzzles(data).unwrap().constraints_of::<Thermo>().count(), 3);
+        assert!(matches!(from_fpuzzles("load=N4Igzglg"), Err(QuadrataError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_from_fpuzzles_json() {
+        let thermos = r#","thermometer":[{"lines":[["R1C1","R1C2","R1C3"]]},{"lines":[["R4C4","R3C4"],["R3C1","R4C1","R4C2"]]}],"title":"Thermos""#;
+        let puzzle = from_fpuzzles(&json("0004001000000000", thermos)).unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 4, 3, 1, 2, 2, 1, 4, 3, 3, 4, 2, 1]);
+
+        // clues that fit the same solution
+        let clues = r#","killercage":[{"cells":["R1C1","R1C2"],"value":"3"}],"arrow":[{"cells":["R1C4"],"lines":[["R1C4","R2C3","R3C4"]]}],"littlekillersum":[{"cell":"R0C0","direction":"DR","value":9}],"ratio":[{"cells":["R1C1","R1C2"]}]"#;
+        let puzzle = from_fpuzzles(&json("0004001000000000", &[thermos, clues].concat())).unwrap();
+        assert_eq!(puzzle.constraints_of::<Cage>().next().unwrap(), &Cage { cells: vec![0, 1], sum: 3 });
+        assert_eq!(puzzle.constraints_of::<Arrow>().next().unwrap().path, vec![6, 11]);
+        assert_eq!(puzzle.constraints_of::<LittleKiller>().next().unwrap().cells, vec![0, 5, 10, 15]);
+        assert_eq!(puzzle.constraints_of::<Edges>().next().unwrap().marks, vec![(0, 1, EdgeKind::Black)]);
+
+        // an extra region only stops digits repeating so it can have fewer cells than a box
+        let region = r#","extraregion":[{"cells":["R1C1","R2C2","R3C3"]}]"#;
+        let puzzle = from_fpuzzles(&json("0004001000000000", &[thermos, region].concat())).unwrap();
+        assert_eq!(puzzle.constraints_of::<ExtraRegion>().next().unwrap().cells, vec![0, 5, 10]);
+        assert_eq!(puzzle.constraints_of::<Cage>().count(), 0);
+
+        let unsupported = from_fpuzzles(&json("0000000000000000", r#","minimum":[{"cell":"R1C1"}]"#));
+        assert_eq!(unsupported.err(), Some(QuadrataError::UnsupportedConstraints));
+        let unknown = [thermos, r#","sudokuwiggle":true"#].concat();
+        let unknown = from_fpuzzles(&json("0004001000000000", &unknown));
+        assert_eq!(unknown.err(), Some(QuadrataError::UnsupportedConstraints));
+        let unused = r#","minimum":[],"antiknight":false,"text":[{"cells":["R1C1"],"value":"?"}]"#;
+        assert!(from_fpuzzles(&json("0004001000000000", &[thermos, unused].concat())).is_ok());
+        let far = from_fpuzzles(&json("0000000000000000", r#","xv":[{"cells":["R1C1","R3C1"],"value":"X"}]"#));
+        assert!(matches!(far.err(), Some(QuadrataError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_from_fpuzzles_regions() {
+        // the jigsaw from `io`, with its regions numbered from 0
+        let regions = [0, 0, 0, 1, 2, 0, 1, 1, 2, 2, 3, 1, 2, 3, 3, 3];
+        let givens = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 3, 0, 1];
+        let cells: Vec<String> = regions
+            .iter()
+            .zip(givens)
+            .map(|(r, g)| match g {
+                0 => format!("{{\"region\":{}}}", r),
+                _ => format!("{{\"region\":{},\"value\":{},\"given\":true}}", r, g),
+            })
+            .collect();
+        let rows: Vec<String> = cells.chunks(4).map(|r| format!("[{}]", r.join(","))).collect();
+        let text = format!("{{\"size\":4,\"grid\":[{}]}}", rows.join(","));
+        let puzzle = from_fpuzzles(&text).unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 2, 4, 1, 3, 3, 1, 4, 2, 4, 3, 2, 1]);
+        let text = text.replace("\"region\":3", "\"region\":0");
+        assert!(matches!(from_fpuzzles(&text).err(), Some(QuadrataError::InvalidRegion { .. })));
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::solver::minimize_in_order;
+use crate::{box_shape, Puzzle, QuadrataError};
+
+/// A small xorshift generator so puzzles can be reproduced from a seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u32) -> Rng {
+        // xorshift gets stuck at 0 so mix the seed into a nonzero state
+        Rng((seed as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// A random ordering of `0..n` made by shuffling `n / group` groups of `group` items and
+    /// then the items within each group.
+    fn grouped_order(&mut self, n: usize, group: usize) -> Vec<usize> {
+        let mut groups: Vec<usize> = (0..n / group).collect();
+        self.shuffle(&mut groups);
+        let mut order = Vec::with_capacity(n);
+        for g in groups {
+            let mut members: Vec<usize> = (g * group..(g + 1) * group).collect();
+            self.shuffle(&mut members);
+            order.extend(members);
+        }
+        order
+    }
+}
+
+/// Generate a random puzzle with a unique solution and no redundant givens.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn generate(size: usize, seed: u32) -> Result<Puzzle, QuadrataError> {
+    let (box_rows, box_cols) = box_shape(size, false);
+    if box_rows < 2 || size > 32 {
+        return Err(QuadrataError::UnsupportedSize(size));
+    }
+    let mut rng = Rng::new(seed);
+
+    // start from a patterned solution and shuffle it while keeping it valid
+    let mut digits: Vec<u8> = (1..=size as u8).collect();
+    rng.shuffle(&mut digits);
+    let rows = rng.grouped_order(size, box_rows);
+    let cols = rng.grouped_order(size, box_cols);
+    let mut grid = vec![0; size * size];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, col) in cols.iter().enumerate() {
+            let pattern = (box_cols * (row % box_rows) + row / box_rows + col) % size;
+            grid[r * size + c] = digits[pattern];
+        }
+    }
+
+    let mut order: Vec<usize> = (0..size * size).collect();
+    rng.shuffle(&mut order);
+    for i in minimize_in_order(&Puzzle::raw_from_grid(&grid), order)? {
+        grid[i] = 0;
+    }
+    Puzzle::from_grid(&grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::is_minimal;
+
+    #[test]
+    fn test_generate() {
+        let puzzle = generate(4, 1).unwrap();
+        assert_eq!(puzzle.size, 4);
+        assert_eq!(is_minimal(&puzzle), Ok(true));
+
+        let puzzle = generate(9, 42).unwrap();
+        assert_eq!(is_minimal(&puzzle), Ok(true));
+        assert_eq!(generate(9, 42).unwrap().to_grid(), puzzle.to_grid());
+        assert_ne!(generate(9, 43).unwrap().to_grid(), puzzle.to_grid());
+
+        // rectangular boxes for the sizes that aren't square
+        for size in [6, 8, 10] {
+            let puzzle = generate(size, 1).unwrap();
+            assert_eq!(puzzle.solution().len(), size * size);
+        }
+        assert_eq!(generate(7, 1).err(), Some(QuadrataError::UnsupportedSize(7)));
+    }
+}
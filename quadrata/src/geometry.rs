@@ -0,0 +1,172 @@
+/// Where the cells of a puzzle sit on the page, which cells are next to each other and which
+/// units (the rows, columns, boxes and any other regions) they're in.
+///
+/// Cells are numbered in reading order, but a puzzle made of several overlapping grids skips
+/// the spots no grid covers, so anything that moves between cells should go through here
+/// rather than doing arithmetic on the cell numbers.
+///
+/// Units are numbered through every region set of `Puzzle::boxes` in turn (so the rows of a
+/// 9x9 are units 0 to 8, the columns 9 to 17 and so on) and the cells of each unit and the
+/// peers of each cell are worked out once up front.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Geometry {
+    /// How many columns the grid (or the box around several overlapping grids) has.
+    pub width: usize,
+    /// Where each cell sits in the grid, counting across its rows of `width` cells. Puzzles
+    /// with overlapping grids leave out the spots no grid covers.
+    pub positions: Vec<usize>,
+    /// If moving off one edge of the grid comes back in on the opposite edge (a torus).
+    pub wraps: bool,
+    /// The cells in each unit.
+    units: Vec<Vec<usize>>,
+    /// Which region set each unit comes from.
+    unit_sets: Vec<usize>,
+    /// The units each cell is in.
+    cell_units: Vec<Vec<usize>>,
+    /// The other cells sharing a unit with each cell.
+    peers: Vec<Vec<usize>>,
+}
+
+impl Geometry {
+    /// A grid `width` cells across with cells at `positions` (see `Geometry::positions`).
+    pub fn new(width: usize, positions: Vec<usize>) -> Geometry {
+        Geometry { width, positions, ..Default::default() }
+    }
+
+    /// A plain square grid with `size` rows and columns.
+    pub fn square(size: usize) -> Geometry {
+        Geometry::new(size, (0..size * size).collect())
+    }
+
+    /// Work out the units from region sets with an id (from 1) for every cell, or 0 for the
+    /// cells a set leaves out.
+    pub(crate) fn index_units(&mut self, sets: &[Vec<usize>]) {
+        self.units.clear();
+        self.unit_sets.clear();
+        self.cell_units = vec![Vec::new(); self.positions.len()];
+        for (set, regions) in sets.iter().enumerate() {
+            let first = self.units.len();
+            let n_regions = regions.iter().max().copied().unwrap_or(0);
+            self.units.extend(vec![Vec::new(); n_regions]);
+            self.unit_sets.extend(vec![set; n_regions]);
+            for (cell, region) in regions.iter().enumerate().filter(|(_, r)| **r != 0) {
+                self.units[first + region - 1].push(cell);
+                self.cell_units[cell].push(first + region - 1);
+            }
+        }
+        self.peers = (0..self.positions.len())
+            .map(|cell| {
+                let mut peers: Vec<usize> =
+                    self.cell_units[cell].iter().flat_map(|u| self.units[*u].iter().copied()).collect();
+                peers.sort_unstable();
+                peers.dedup();
+                peers.retain(|p| *p != cell);
+                peers
+            })
+            .collect();
+    }
+
+    /// The row of a cell in the grid.
+    pub fn row(&self, cell: usize) -> usize {
+        self.positions[cell] / self.width
+    }
+
+    /// The column of a cell in the grid.
+    pub fn col(&self, cell: usize) -> usize {
+        self.positions[cell] % self.width
+    }
+
+    /// The row and column of a cell in the grid.
+    pub fn row_col(&self, cell: usize) -> (usize, usize) {
+        (self.positions[cell] / self.width, self.positions[cell] % self.width)
+    }
+
+    /// The cell at a row and column of the grid, if there's one there.
+    pub fn cell_at(&self, row: isize, col: isize) -> Option<usize> {
+        let (row, col) = if self.wraps {
+            (row.rem_euclid(self.height() as isize), col.rem_euclid(self.width as isize))
+        } else {
+            (row, col)
+        };
+        if row < 0 || col < 0 || col >= self.width as isize {
+            return None;
+        }
+        self.positions.binary_search(&(row as usize * self.width + col as usize)).ok()
+    }
+
+    /// The cell `dr` rows down and `dc` columns across from `cell`, if there's one there.
+    pub fn offset(&self, cell: usize, (dr, dc): (isize, isize)) -> Option<usize> {
+        let (row, col) = self.row_col(cell);
+        self.cell_at(row as isize + dr, col as isize + dc)
+    }
+
+    /// If two cells share an edge.
+    pub fn touching(&self, a: usize, b: usize) -> bool {
+        [(0, 1), (1, 0), (0, -1), (-1, 0)].into_iter().any(|step| self.offset(a, step) == Some(b))
+    }
+
+    /// How many rows the grid has.
+    pub fn height(&self) -> usize {
+        self.positions.last().map_or(0, |p| p / self.width + 1)
+    }
+
+    /// How many units there are over all the region sets.
+    pub fn n_units(&self) -> usize {
+        self.units.len()
+    }
+
+    /// The cells in a unit, in order.
+    pub fn cells_in(&self, unit: usize) -> &[usize] {
+        &self.units[unit]
+    }
+
+    /// The units a cell is in, one for every region set that covers it.
+    pub fn units_of(&self, cell: usize) -> &[usize] {
+        &self.cell_units[cell]
+    }
+
+    /// The unit of region set `set` (0 for rows, 1 for columns, 2 for boxes and so on) that a
+    /// cell is in, if that set covers it.
+    pub fn unit_in(&self, set: usize, cell: usize) -> Option<usize> {
+        self.cell_units[cell].iter().copied().find(|u| self.unit_sets[*u] == set)
+    }
+
+    /// The other cells that share a unit with a cell.
+    pub fn peers(&self, cell: usize) -> &[usize] {
+        &self.peers[cell]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapping() {
+        let mut geometry = Geometry::square(4);
+        assert_eq!(geometry.cell_at(-1, 0), None);
+        assert_eq!(geometry.offset(3, (0, 1)), None);
+        assert!(!geometry.touching(3, 0));
+        geometry.wraps = true;
+        assert!(geometry.touching(3, 0));
+        assert_eq!(geometry.cell_at(-1, 0), Some(12));
+        assert_eq!(geometry.offset(3, (0, 1)), Some(0));
+        assert_eq!(geometry.offset(0, (-2, -1)), Some(11));
+    }
+
+    #[test]
+    fn test_units() {
+        let mut geometry = Geometry::square(4);
+        let rows = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+        let diagonal = vec![1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1];
+        geometry.index_units(&[rows, diagonal]);
+        assert_eq!(geometry.n_units(), 5);
+        assert_eq!(geometry.cells_in(1), &[4, 5, 6, 7]);
+        assert_eq!(geometry.units_of(5), &[1, 4]);
+        assert_eq!(geometry.units_of(6), &[1]);
+        assert_eq!(geometry.unit_in(1, 10), Some(4));
+        assert_eq!(geometry.unit_in(1, 9), None);
+        assert_eq!(geometry.peers(5), &[0, 4, 6, 7, 10, 15]);
+        assert_eq!((geometry.row(6), geometry.col(6)), (1, 2));
+    }
+}
@@ -1,21 +1,715 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::Puzzle;
+use crate::constraints::outside::ray;
+use crate::solver::solve;
+use crate::{symbol, symbol_digit, EdgeKind, Edges, Puzzle, QuadrataError};
 
-#[wasm_bindgen]
-pub fn from_81(text: &str) -> Result<Puzzle, JsError> {
-    if text.len() != 81 {
-        return Err(JsError::new("text must be 81 characters long"));
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn from_81(text: &str) -> Result<Puzzle, QuadrataError> {
+    Puzzle::from_grid(&parse_81(text)?)
+}
+
+fn parse_81(text: &str) -> Result<Vec<u8>, QuadrataError> {
+    let length = text.chars().count();
+    if length != 81 {
+        return Err(QuadrataError::InvalidLength { expected: 81, found: length });
     }
+    parse_grid(text)
+}
+
+/// The number of cells in the square grid (up to 32x32) closest to `found` cells, to report
+/// with a grid that isn't square. Nothing at all is taken as a missing 9x9.
+fn closest_grid_length(found: usize) -> usize {
+    if found == 0 {
+        return 81;
+    }
+    let size = ((found as f64).sqrt().round() as usize).clamp(1, 32);
+    size * size
+}
+
+/// Read a square grid with blanks for empty cells, either with one symbol per cell (`1` to `9`
+/// then `A` to `W`, so up to 32x32) or with the cells as numbers separated by commas.
+pub fn parse_grid(text: &str) -> Result<Vec<u8>, QuadrataError> {
+    if text.contains(',') {
+        return parse_numbers(text);
+    }
+    let length = text.chars().count();
+    let size = (length as f64).sqrt() as usize;
+    if size * size != length || !(1..=32).contains(&size) {
+        let expected = closest_grid_length(length);
+        return Err(QuadrataError::InvalidLength { expected, found: length });
+    }
+    parse_digits(text, size)
+}
+
+/// Read single symbol cells from 1 to `size` with blanks for empty cells.
+fn parse_digits(text: &str, size: usize) -> Result<Vec<u8>, QuadrataError> {
     let mut truths: Vec<u8> = Vec::new();
-    for c in text.chars() {
+    for (pos, c) in text.chars().enumerate() {
         match c {
             '0' | '.' | ' ' | 'X' | 'x' => truths.push(0),
-            '1'..='9' => truths.push(str::parse::<u8>(&c.to_string())?),
-            _ => {
-                return Err(JsError::new(&format!("character {} can not be present", c)));
+            _ => match symbol_digit(c) {
+                Some(digit) if digit <= size => truths.push(digit as u8),
+                _ => return Err(QuadrataError::InvalidCharacter { pos, ch: c }),
+            },
+        }
+    }
+    Ok(truths)
+}
+
+/// Read comma-separated numbers from 1 to the size of the grid, with `0`, `.` or nothing for
+/// empty cells (e.g. `0,12,.,25,...` for a 25x25).
+fn parse_numbers(text: &str) -> Result<Vec<u8>, QuadrataError> {
+    let cells: Vec<&str> = text.split(',').collect();
+    let size = (cells.len() as f64).sqrt() as usize;
+    if size * size != cells.len() || !(1..=32).contains(&size) {
+        let found = cells.len();
+        return Err(QuadrataError::InvalidLength { expected: closest_grid_length(found), found });
+    }
+    let mut truths: Vec<u8> = Vec::new();
+    let mut pos = 0;
+    for cell in cells {
+        match cell.trim() {
+            "" | "0" | "." => truths.push(0),
+            number => match number.parse::<u8>() {
+                Ok(digit) if (1..=size).contains(&(digit as usize)) => truths.push(digit),
+                _ => {
+                    let skipped = cell.chars().take_while(|c| c.is_whitespace()).count();
+                    let ch = number.chars().next().unwrap_or(' ');
+                    return Err(QuadrataError::InvalidCharacter { pos: pos + skipped, ch });
+                }
+            },
+        }
+        pos += cell.chars().count() + 1;
+    }
+    Ok(truths)
+}
+
+/// Read a map of regions with one character per cell; every distinct character is a region
+/// and they're numbered in the order they first appear, except `.` for cells in none of them.
+fn parse_regions(text: &str) -> Vec<usize> {
+    let mut seen: Vec<char> = Vec::new();
+    text.chars()
+        .map(|c| match seen.iter().position(|s| *s == c) {
+            _ if c == '.' => 0,
+            Some(ix) => ix + 1,
+            None => {
+                seen.push(c);
+                seen.len()
+            }
+        })
+        .collect()
+}
+
+/// Read a jigsaw puzzle from its givens and a map of its regions (e.g. `AAABBB...`).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn from_jigsaw(text: &str, regions: &str) -> Result<Puzzle, QuadrataError> {
+    let mut puzzle = Puzzle::raw_from_grid(&parse_grid(text)?);
+    puzzle.set_regions(parse_regions(regions))?;
+    puzzle.truths = solve(&puzzle)?;
+    Ok(puzzle)
+}
+
+/// Where the top left cell of each grid sits in the puzzles made of overlapping 9x9 grids.
+fn layout_origins(name: &str) -> Option<&'static [(usize, usize)]> {
+    match name {
+        "samurai" => Some(&[(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)]),
+        "flower" => Some(&[(0, 6), (6, 0), (6, 6), (6, 12), (12, 6)]),
+        "twin" => Some(&[(0, 0), (6, 6)]),
+        "butterfly" => Some(&[(0, 0), (0, 3), (3, 0), (3, 3)]),
+        _ => None,
+    }
+}
+
+/// Read the givens of overlapping 9x9 grids, with every spot of the box around the grids
+/// written out row by row (and left blank where there's no grid).
+fn parse_layout(text: &str, origins: &[(usize, usize)]) -> Result<Puzzle, QuadrataError> {
+    let width = origins.iter().map(|o| o.1 + 9).max().unwrap_or(0);
+    let height = origins.iter().map(|o| o.0 + 9).max().unwrap_or(0);
+    let length = text.chars().count();
+    if length != width * height {
+        return Err(QuadrataError::InvalidLength { expected: width * height, found: length });
+    }
+    let grid = parse_digits(text, 9)?;
+    let puzzle = Puzzle::raw_from_layout(9, origins, &grid);
+    for (pos, ch) in text.chars().enumerate() {
+        if grid[pos] != 0 && puzzle.geometry.positions.binary_search(&pos).is_err() {
+            return Err(QuadrataError::InvalidCharacter { pos, ch });
+        }
+    }
+    Ok(puzzle)
+}
+
+/// Read a cell written like `r1c2` (1-based row then column).
+pub(crate) fn parse_cell(text: &str, puzzle: &Puzzle) -> Option<usize> {
+    let (row, col) = text.strip_prefix(['r', 'R'])?.split_once(['c', 'C'])?;
+    let (row, col): (isize, isize) = (row.parse().ok()?, col.parse().ok()?);
+    puzzle.cell_at(row - 1, col - 1)
+}
+
+fn parse_cells<'a>(words: impl Iterator<Item = &'a str>, puzzle: &Puzzle) -> Option<Vec<usize>> {
+    let cells: Vec<usize> = words.map(|w| parse_cell(w, puzzle)).collect::<Option<_>>()?;
+    if cells.is_empty() {
+        return None;
+    }
+    Some(cells)
+}
+
+/// Read a variant puzzle: a line of givens followed by one constraint per line.
+///
+/// Cells are written like `r1c2` and the constraints are:
+///
+/// - `cage <sum> <cells...>`: a killer cage (with a sum of 0 if it has no total)
+/// - `thermo <cells...>`: a thermometer starting from the bulb
+/// - `arrow <circle cells...> > <path cells...>`: an arrow (with a pill for several circle cells)
+/// - `whisper`, `renban`, `palindrome` or `regionsum <cells...>`: a line through the cells
+/// - `diagonals`: the main diagonals are regions too (X-Sudoku)
+/// - `windows`: the extra boxes of Hyper Sudoku are regions too
+/// - `disjoint`: the cells in the same position of every box form regions too (not with a
+///   `layout`)
+/// - `antiknight`, `antiking`: cells a chess knight's or king's move apart can't repeat a digit
+/// - `white`, `black`, `x` or `v <cell> <cell>`: a Kropki dot or XV sum between neighbors
+/// - `greater <cell> <cell>`: a greater-than sign between neighbors, with the bigger digit first
+/// - `negative <kinds...>`: neighbors without a `white`, `black`, `x` or `v` clue can't fit one
+/// - `nonconsecutive`: neighboring cells can't have consecutive digits
+/// - `even`, `odd <cells...>`: shaded cells that only hold even or odd digits
+/// - `sandwich`, `xsum <sum> <clue>`: a clue outside the grid (e.g. `r0c3` above column 3
+///   or `r3c10` right of row 3) about the row or column it faces
+/// - `littlekiller <sum> <clue> <dr|dl|ur|ul>`: a diagonal sum starting outside the grid
+/// - `regions <map>`: irregular regions in place of the boxes (see `from_jigsaw`)
+/// - `colors <map>`: cells of the same color can't repeat a digit (Colored Sudoku), with the
+///   map written like the regions, `.` for cells without a color, and as many cells of each
+///   color as there are digits
+/// - `boxes <wide|tall>`: which way the rectangular boxes of e.g. a 6x6 or 12x12 lie (wide,
+///   with more columns than rows, if not given), but not with a `layout`
+/// - `torus`: the edges of the grid join up with the opposite ones, so neighbors and chess
+///   moves wrap around (and there's no outside for clues)
+/// - `layout <samurai|flower|twin|butterfly>`: overlapping 9x9 grids, with the givens line
+///   covering the box around all of them (and cells numbered by their place in it)
+///
+/// Blank lines and lines starting with `#` are skipped.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn from_variant(text: &str) -> Result<Puzzle, QuadrataError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let (_, givens) = lines
+        .next()
+        .ok_or(QuadrataError::InvalidLength { expected: closest_grid_length(0), found: 0 })?;
+    let lines: Vec<(usize, &str)> = lines.collect();
+    // the layout decides how to read the givens so it has to come first
+    let layout = lines.iter().find(|(_, text)| text.split_whitespace().next() == Some("layout"));
+    let mut puzzle = match layout {
+        Some((line, text)) => {
+            let invalid = || QuadrataError::InvalidConstraint { line: *line, text: text.to_string() };
+            let name = text.split_whitespace().nth(1).ok_or_else(invalid)?;
+            parse_layout(givens, layout_origins(name).ok_or_else(invalid)?)?
+        }
+        None => Puzzle::raw_from_grid(&parse_grid(givens)?),
+    };
+    // wrapping around changes which cells are next to each other so it comes first too
+    if lines.iter().any(|(_, text)| text.split_whitespace().next() == Some("torus")) {
+        puzzle.set_torus(true);
+    }
+    // the edge clues all go into one constraint since the negative clues depend on every mark
+    let mut edges = Edges::default();
+    for (line, text) in lines {
+        let invalid = || QuadrataError::InvalidConstraint { line, text: text.to_string() };
+        let mut words = text.split_whitespace();
+        match words.next() {
+            Some("cage") => {
+                let sum = words.next().and_then(|w| w.parse().ok()).ok_or_else(invalid)?;
+                let cells = parse_cells(words, &puzzle).ok_or_else(invalid)?;
+                puzzle.add_cage(cells, sum);
+            }
+            Some("thermo") => puzzle.add_thermo(parse_cells(words, &puzzle).ok_or_else(invalid)?),
+            Some("arrow") => {
+                let rest: Vec<&str> = words.collect();
+                let split = rest.iter().position(|w| *w == ">").ok_or_else(invalid)?;
+                let circle = parse_cells(rest[..split].iter().copied(), &puzzle).ok_or_else(invalid)?;
+                let path = parse_cells(rest[split + 1..].iter().copied(), &puzzle).ok_or_else(invalid)?;
+                puzzle.add_arrow(circle, path);
+            }
+            Some(kind @ ("whisper" | "renban" | "palindrome" | "regionsum")) => {
+                let cells = parse_cells(words, &puzzle).ok_or_else(invalid)?;
+                match kind {
+                    "whisper" => puzzle.add_whisper(cells),
+                    "renban" => puzzle.add_renban(cells),
+                    "palindrome" => puzzle.add_palindrome(cells),
+                    _ => puzzle.add_region_sum_line(cells),
+                }
+            }
+            Some("diagonals") => puzzle.add_diagonals(),
+            Some("windows") => puzzle.add_windows(),
+            Some("disjoint") => puzzle.add_disjoint_groups()?,
+            Some(kind @ ("white" | "black" | "x" | "v" | "greater")) => {
+                let cells = parse_cells(words, &puzzle).ok_or_else(invalid)?;
+                let [a, b] = cells[..] else {
+                    return Err(invalid());
+                };
+                if !puzzle.geometry().touching(a, b) {
+                    return Err(invalid());
+                }
+                edges.marks.push((a, b, parse_edge_kind(kind).ok_or_else(invalid)?));
             }
+            Some("negative") => {
+                for kind in words {
+                    // a missing sign doesn't say anything about the digits
+                    match parse_edge_kind(kind).ok_or_else(invalid)? {
+                        EdgeKind::Greater => return Err(invalid()),
+                        kind => edges.negative.push(kind),
+                    }
+                }
+            }
+            Some("nonconsecutive") => edges.negative.push(EdgeKind::White),
+            Some("even") => puzzle.add_even(parse_cells(words, &puzzle).ok_or_else(invalid)?),
+            Some("odd") => puzzle.add_odd(parse_cells(words, &puzzle).ok_or_else(invalid)?),
+            Some(kind @ ("sandwich" | "xsum" | "littlekiller")) => {
+                let sum = words.next().and_then(|w| w.parse().ok()).ok_or_else(invalid)?;
+                let clue = words.next().and_then(|w| parse_outside(w, &puzzle)).ok_or_else(invalid)?;
+                let step = match (kind, words.next()) {
+                    ("littlekiller", Some("dr")) => (1, 1),
+                    ("littlekiller", Some("dl")) => (1, -1),
+                    ("littlekiller", Some("ur")) => (-1, 1),
+                    ("littlekiller", Some("ul")) => (-1, -1),
+                    ("littlekiller", _) => return Err(invalid()),
+                    // the others face straight into the grid
+                    (_, None) => facing(clue, &puzzle).ok_or_else(invalid)?,
+                    (_, Some(_)) => return Err(invalid()),
+                };
+                let cells = ray(&puzzle, clue, step);
+                match kind {
+                    _ if cells.is_empty() => return Err(invalid()),
+                    "sandwich" => puzzle.add_sandwich(cells, sum),
+                    "xsum" => puzzle.add_x_sum(cells, sum),
+                    _ => puzzle.add_little_killer(cells, sum),
+                }
+            }
+            Some("antiknight") => puzzle.add_anti_knight(),
+            Some("antiking") => puzzle.add_anti_king(),
+            Some("layout" | "torus") => {}
+            Some("colors") => {
+                let map = words.next().ok_or_else(invalid)?;
+                // anything past the colors that fit is an error either way
+                let colors = parse_regions(map).into_iter().map(|c| c.min(u8::MAX as usize) as u8);
+                puzzle.add_colors(colors.collect())?;
+            }
+            Some("boxes") => match words.next() {
+                Some("wide") => puzzle.set_tall_boxes(false)?,
+                Some("tall") => puzzle.set_tall_boxes(true)?,
+                _ => return Err(invalid()),
+            },
+            Some("regions") => {
+                let map = words.next().ok_or_else(invalid)?;
+                puzzle.set_regions(parse_regions(map))?;
+            }
+            _ => return Err(invalid()),
         }
     }
-    Puzzle::from_grid(&truths).map_err(|s| JsError::new(&s))
+    if edges != Edges::default() {
+        puzzle.add_constraint(edges);
+    }
+    puzzle.truths = solve(&puzzle)?;
+    Ok(puzzle)
+}
+
+/// Read the position of a clue outside the grid (e.g. `r0c3`) as a 0-based row and column,
+/// so the rows and columns just outside are -1 and the width or height.
+pub(crate) fn parse_outside(text: &str, puzzle: &Puzzle) -> Option<(isize, isize)> {
+    let (row, col) = text.strip_prefix(['r', 'R'])?.split_once(['c', 'C'])?;
+    let (row, col): (isize, isize) = (row.parse().ok()?, col.parse().ok()?);
+    puzzle.cell_at(row - 1, col - 1).is_none().then_some((row - 1, col - 1))
+}
+
+/// The direction into the grid from a clue beside a row or column (if only one way leads in).
+pub(crate) fn facing((row, col): (isize, isize), puzzle: &Puzzle) -> Option<(isize, isize)> {
+    let steps: Vec<(isize, isize)> = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .filter(|(dr, dc)| puzzle.cell_at(row + dr, col + dc).is_some())
+        .collect();
+    match steps[..] {
+        [step] => Some(step),
+        _ => None,
+    }
+}
+
+fn parse_edge_kind(text: &str) -> Option<EdgeKind> {
+    match text {
+        "white" => Some(EdgeKind::White),
+        "black" => Some(EdgeKind::Black),
+        "x" => Some(EdgeKind::X),
+        "v" => Some(EdgeKind::V),
+        "greater" => Some(EdgeKind::Greater),
+        _ => None,
+    }
+}
+
+/// Write out the puzzle's givens and entered values with `0` for empty cells (and letters
+/// from `A` for the digits past 9).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn to_81(puzzle: &Puzzle) -> String {
+    puzzle.to_grid().iter().map(|v| if *v == 0 { '0' } else { symbol(*v as usize) }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arrow, Line, LittleKiller};
+
+    #[test]
+    fn test_from_81() {
+        let puzzle = from_81(
+            "016400000200009000400000062070230100100000003003087040960000005000800007000006820",
+        )
+        .unwrap();
+        assert_eq!(puzzle.size, 9);
+        assert!(puzzle.truths.iter().all(|t| *t != 0));
+        assert_eq!(
+            to_81(&puzzle),
+            "016400000200009000400000062070230100100000003003087040960000005000800007000006820",
+        );
+
+        assert_eq!(
+            from_81("0164").err(),
+            Some(QuadrataError::InvalidLength { expected: 81, found: 4 })
+        );
+        assert_eq!(
+            from_81(
+                "01640000020000900040000006207023010010000000300308704096000000500080000700000682?",
+            )
+            .err(),
+            Some(QuadrataError::InvalidCharacter { pos: 80, ch: '?' })
+        );
+        assert!(matches!(
+            from_81(
+                "000000000000000000000000000000000000000000000000000000000000000000000000000000001",
+            ),
+            Err(QuadrataError::MultipleSolutions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_variant() {
+        // the killer from the Wikipedia article on killer sudoku
+        let puzzle = from_variant(
+            "
+            000000000000000000000000000000000000000000000000000000000000000000000000000000000
+            cage 3 r1c1 r1c2
+            cage 15 r1c3 r1c4 r1c5
+            cage 22 r1c6 r2c5 r2c6 r3c5
+            cage 4 r1c7 r2c7
+            cage 16 r1c8 r2c8
+            cage 15 r1c9 r2c9 r3c9 r4c9
+            cage 25 r2c1 r2c2 r3c1 r3c2
+            cage 17 r2c3 r2c4
+            cage 9 r3c3 r3c4 r4c4
+            cage 8 r3c6 r4c6 r5c6
+            cage 20 r3c7 r3c8 r4c7
+            cage 6 r4c1 r5c1
+            cage 14 r4c2 r4c3
+            cage 17 r4c5 r5c5 r6c5
+            cage 17 r4c8 r5c7 r5c8
+            cage 13 r5c2 r5c3 r6c2
+            cage 20 r5c4 r6c4 r7c4
+            cage 12 r5c9 r6c9
+            cage 27 r6c1 r7c1 r8c1 r9c1
+            cage 6 r6c3 r7c2 r7c3
+            cage 20 r6c6 r7c6 r7c7
+            cage 6 r6c7 r6c8
+            cage 10 r7c5 r8c4 r8c5 r9c4
+            cage 14 r7c8 r7c9 r8c8 r8c9
+            cage 8 r8c2 r9c2
+            cage 16 r8c3 r9c3
+            cage 15 r8c6 r8c7
+            cage 13 r9c5 r9c6 r9c7
+            cage 17 r9c8 r9c9
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            puzzle.solution(),
+            parse_81("215647398368952174794381652586274931142593867973816425821739546659428713437165289")
+                .unwrap(),
+        );
+
+        assert_eq!(
+            from_variant("000000000000000000000000000000000000000000000000000000000000000000000000000000000\ncage x r1c1").err(),
+            Some(QuadrataError::InvalidConstraint { line: 2, text: "cage x r1c1".to_string() }),
+        );
+        assert_eq!(
+            from_variant("000000000000000000000000000000000000000000000000000000000000000000000000000000000\ncage 3 r1c1 r10c1").err(),
+            Some(QuadrataError::InvalidConstraint { line: 2, text: "cage 3 r1c1 r10c1".to_string() }),
+        );
+    }
+
+    #[test]
+    fn test_from_jigsaw() {
+        let puzzle = from_jigsaw("0000000000020301", "AAABCABBCCDBCDDD").unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 2, 4, 1, 3, 3, 1, 4, 2, 4, 3, 2, 1]);
+
+        let puzzle = from_variant("0000000000020301\nregions 1112312233423444").unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 2, 4, 1, 3, 3, 1, 4, 2, 4, 3, 2, 1]);
+
+        assert_eq!(
+            from_jigsaw("0000000000020301", "AAAACABBCCDBCDDD").err(),
+            Some(QuadrataError::InvalidRegion { region: 1, cells: 5 }),
+        );
+        assert_eq!(
+            from_jigsaw("0000000000020305", "AAABCABBCCDBCDDD").err(),
+            Some(QuadrataError::InvalidCharacter { pos: 15, ch: '5' }),
+        );
+    }
+
+    #[test]
+    fn test_large_grids() {
+        // a 25x25 with every row shifted along from the one above and a few cells blanked out
+        let solution: Vec<u8> =
+            (0..625).map(|i| ((i / 25 % 5 * 5 + i / 125 + i % 25) % 25 + 1) as u8).collect();
+        let text: String =
+            (0..625).map(|i| if i % 7 == 3 { '.' } else { symbol(solution[i] as usize) }).collect();
+        assert_eq!(&text[..27], "123.56789A.CDEFGH.JKLMNO.67");
+        let puzzle = from_variant(&text).unwrap();
+        assert_eq!(puzzle.size, 25);
+        assert_eq!(puzzle.solution(), solution);
+        assert_eq!(puzzle.guesses()[4], "5");
+        assert_eq!(to_81(&puzzle), text.replace('.', "0"));
+
+        let numbers: Vec<String> = text.chars().map(|c| symbol_digit(c).unwrap_or(0).to_string()).collect();
+        let puzzle = from_variant(&numbers.join(",")).unwrap();
+        assert_eq!(puzzle.solution(), solution);
+
+        assert_eq!(
+            from_variant("1,2,3,4,0,0,0,0,0,0,0,0,0,0,0,5").err(),
+            Some(QuadrataError::InvalidCharacter { pos: 30, ch: '5' }),
+        );
+        assert_eq!(
+            from_variant("123G000000000000").err(),
+            Some(QuadrataError::InvalidCharacter { pos: 3, ch: 'G' }),
+        );
+
+        // grids that aren't square are measured against the nearest one
+        assert_eq!(
+            from_variant(&"0".repeat(255)).err(),
+            Some(QuadrataError::InvalidLength { expected: 256, found: 255 }),
+        );
+        assert_eq!(
+            from_variant(&vec!["0"; 15].join(",")).err(),
+            Some(QuadrataError::InvalidLength { expected: 16, found: 15 }),
+        );
+        assert_eq!(
+            from_variant("# no grid").err(),
+            Some(QuadrataError::InvalidLength { expected: 81, found: 0 }),
+        );
+    }
+
+    #[test]
+    fn test_from_variant_colors() {
+        assert!(matches!(from_variant("1230000000000000"), Err(QuadrataError::MultipleSolutions { .. })));
+        let puzzle = from_variant("1230000000000000\ncolors ABCDCDABBADCDCBA").unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 4, 3, 2, 1, 3, 4, 1, 2, 2, 1, 4, 3]);
+        assert_eq!(&puzzle.colors()[..8], &[1, 2, 3, 4, 3, 4, 1, 2]);
+
+        assert_eq!(
+            from_variant("1230000000000000\ncolors AAAAACDABADCDCBA").err(),
+            Some(QuadrataError::InvalidRegion { region: 1, cells: 8 }),
+        );
+    }
+
+    #[test]
+    fn test_rectangular_boxes() {
+        // 2x3 boxes with every fifth cell blank
+        let text = "023450456103234061560234305612012340";
+        let puzzle = from_variant(text).unwrap();
+        assert_eq!(puzzle.solution()[..12], [1, 2, 3, 4, 5, 6, 4, 5, 6, 1, 2, 3]);
+        // the same givens repeat a 2 in the first 3x2 box
+        assert_eq!(from_variant(&format!("{}\nboxes tall", text)).err(), Some(QuadrataError::NoSolution));
+
+        let text = "023450345602561034230561406123012340";
+        let puzzle = from_variant(&format!("{}\nboxes tall", text)).unwrap();
+        assert_eq!(puzzle.solution()[..12], [1, 2, 3, 4, 5, 6, 3, 4, 5, 6, 1, 2]);
+        assert_eq!(from_variant(text).err(), Some(QuadrataError::NoSolution));
+    }
+
+    #[test]
+    fn test_from_variant_thermo() {
+        let puzzle = from_variant(
+            "
+            0004001000000000
+            thermo r1c1 r1c2 r1c3
+            thermo r4c4 r3c4
+            thermo r3c1 r4c1 r4c2
+            ",
+        )
+        .unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 4, 3, 1, 2, 2, 1, 4, 3, 3, 4, 2, 1]);
+    }
+
+    #[test]
+    fn test_from_variant_lines() {
+        let puzzle = from_variant(
+            "
+            0000000000000000
+            whisper r4c1 r3c2 r2c1
+            renban r1c2 r1c1 r2c2 r2c1
+            palindrome r3c4 r3c3 r2c2 r1c3
+            regionsum r3c3 r2c2 r2c3 r1c3
+            ",
+        )
+        .unwrap();
+        assert_eq!(puzzle.constraints_of::<Line>().count(), 4);
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_from_variant_greater_parity() {
+        let puzzle = from_variant(
+            "
+            0000000000000000
+            greater r1c3 r1c2
+            greater r4c1 r4c2
+            greater r4c3 r4c4
+            greater r4c2 r3c2
+            odd r1c1 r3c4
+            ",
+        )
+        .unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1]);
+        assert!(from_variant("0000000000000000\nnegative greater").is_err());
+    }
+
+    #[test]
+    fn test_from_variant_outside() {
+        let puzzle = from_variant(
+            "
+            0000000000000000
+            sandwich 0 r2c0
+            xsum 3 r3c0
+            littlekiller 10 r0c0 dr
+            ",
+        )
+        .unwrap();
+        assert_eq!(puzzle.constraints_of::<LittleKiller>().next().unwrap().cells, vec![0, 5, 10, 15]);
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1]);
+        // clues have to sit outside the grid and sandwiches can't go diagonally
+        assert!(from_variant("0000000000000000\nsandwich 0 r1c1").is_err());
+        assert!(from_variant("0000000000000000\nsandwich 0 r0c0").is_err());
+        assert!(from_variant("0000000000000000\nlittlekiller 3 r0c4 dr").is_err());
+    }
+
+    #[test]
+    fn test_from_variant_layout() {
+        let rows = [
+            "..3.5.7.9   .5.2.6...",
+            "...7.9.2.   6.2.8.1..",
+            "....2.4.6   .8.1.7.5.",
+            "2....4.9.   4.3.5.9.8",
+            ".7....3.4   .2.9.8.4.",
+            "6.4......   8.6.1.5.2",
+            ".1.2.5....2.5.7.2.4.3",
+            ".4.8....1.7.2.8.9.6.7",
+            ".6.3..5.2.6.1.9.7.8..",
+            "      ...75.8.3      ",
+            "      .5.9.2.1.      ",
+            "      4......2.      ",
+            "3.6.8.2.5.3.....23.6.",
+            "5.9.2.7.4......6.9.4.",
+            "1.7.5.8......7.4.8.3.",
+            "2.1.3....   ..5.6.7.3",
+            ".7.6.5.2.   .....5.8.",
+            "8.5.173.6   ......4.5",
+            ".1.3.8.5.   2.3...97.",
+            "7.8.6.4.2   .1......4",
+            "93.5.2.8.   7.6...8..",
+        ];
+        let puzzle = from_variant(&format!("{}\nlayout samurai", rows.concat())).unwrap();
+        assert_eq!((puzzle.geometry.width, puzzle.height(), puzzle.solution().len()), (21, 21, 369));
+        // r10c7 is in the middle grid and r10c1 isn't in any
+        assert_eq!(puzzle.cell_at(9, 6).map(|c| puzzle.row_col(c)), Some((9, 6)));
+        assert!(from_variant(&format!("{}\nlayout samurai\ncage 3 r10c1 r10c2", rows.concat())).is_err());
+
+        let blocked = rows.concat().replacen(' ', "1", 1);
+        assert_eq!(
+            from_variant(&format!("{}\nlayout samurai", blocked)).err(),
+            Some(QuadrataError::InvalidCharacter { pos: 9, ch: '1' }),
+        );
+        assert!(from_variant(&format!("{}\nlayout hexagon", rows.concat())).is_err());
+
+        // the layout decides the boxes
+        assert_eq!(
+            from_variant(&format!("{}\nlayout samurai\nboxes tall", rows.concat())).err(),
+            Some(QuadrataError::UnsupportedConstraints),
+        );
+
+        // the 17 boxes of two grids can't be split into 9 groups
+        assert_eq!(
+            from_variant(&format!("{}\nlayout twin\ndisjoint", ".".repeat(225))).err(),
+            Some(QuadrataError::UnsupportedConstraints),
+        );
+    }
+
+    #[test]
+    fn test_from_variant_edges() {
+        let puzzle = from_variant(
+            "
+            0000000000000000
+            white r1c1 r1c2
+            white r1c2 r1c3
+            black r1c2 r2c2
+            white r1c3 r1c4
+            black r1c4 r2c4
+            white r2c1 r2c2
+            white r2c1 r3c1
+            white r2c3 r2c4
+            white r2c4 r3c4
+            white r3c1 r3c2
+            black r3c1 r4c1
+            white r3c3 r3c4
+            black r3c3 r4c3
+            white r4c1 r4c2
+            white r4c2 r4c3
+            white r4c3 r4c4
+            negative white black
+            ",
+        )
+        .unwrap();
+        assert_eq!(puzzle.constraints_of::<Edges>().count(), 1);
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1]);
+        assert_eq!(
+            from_variant("0000000000000000\nwhite r1c1 r2c2").err(),
+            Some(QuadrataError::InvalidConstraint { line: 2, text: "white r1c1 r2c2".to_string() }),
+        );
+        // the ends of a row are only next to each other on a torus
+        assert_eq!(
+            from_variant("0000000000000000\nwhite r1c1 r1c4").err(),
+            Some(QuadrataError::InvalidConstraint { line: 2, text: "white r1c1 r1c4".to_string() }),
+        );
+        assert!(matches!(
+            from_variant("0000000000000000\nwhite r1c1 r1c4\ntorus"),
+            Err(QuadrataError::MultipleSolutions { .. }),
+        ));
+        assert_eq!(
+            from_variant("0000000000000000\ntorus\nsandwich 0 r0c1").err(),
+            Some(QuadrataError::InvalidConstraint { line: 3, text: "sandwich 0 r0c1".to_string() }),
+        );
+    }
+
+    #[test]
+    fn test_from_variant_arrow() {
+        let givens = "000000000000009000400000062070230100100000003003087040960000005000800007000006820";
+        assert!(matches!(from_81(givens), Err(QuadrataError::MultipleSolutions { .. })));
+        let puzzle = from_variant(&format!(
+            "{}\narrow r1c4 > r1c2 r1c1\narrow r1c3 > r2c1 r1c4\narrow r1c2 r2c1 > r2c2 r1c4",
+            givens,
+        ))
+        .unwrap();
+        assert_eq!(puzzle.constraints_of::<Arrow>().count(), 3);
+        assert_eq!(
+            puzzle.solution(),
+            parse_81("316452978285679314497318562879234156142965783653187249968721435521843697734596821")
+                .unwrap(),
+        );
+    }
 }
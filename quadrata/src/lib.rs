@@ -1,29 +1,101 @@
+mod constraints;
+mod error;
+mod fpuzzles;
+mod generator;
+mod geometry;
 mod io;
+mod lzstring;
+mod scl;
 mod solver;
 mod strategies;
 
-pub use crate::io::from_81;
+pub use crate::constraints::arrow::Arrow;
+pub use crate::constraints::cage::Cage;
+pub use crate::constraints::edges::{EdgeKind, Edges};
+pub use crate::constraints::lines::{Line, LineKind};
+pub use crate::constraints::movement::{AntiKing, AntiKnight};
+pub use crate::constraints::outside::{LittleKiller, Sandwich, XSum};
+pub use crate::constraints::parity::Parity;
+pub use crate::constraints::regions::{ExtraRegion, Regions};
+pub use crate::constraints::thermo::Thermo;
+pub use crate::constraints::Constraint;
+pub use crate::error::QuadrataError;
+pub use crate::fpuzzles::from_fpuzzles;
+pub use crate::generator::generate;
+pub use crate::geometry::Geometry;
+pub use crate::io::{from_81, from_jigsaw, from_variant, parse_grid, to_81};
+pub use crate::scl::{from_scl, to_scl};
+pub use crate::solver::logical_solve;
+pub use crate::strategies::{Solution, Strategy};
 
+use std::any::Any;
+use std::rc::Rc;
+
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use solver::{get_counts, redo_guesses, solve};
+use solver::{
+    ambiguous_cells, bounded_solutions, is_minimal, minimize, rate, redo_guesses, solve, suggest_givens,
+    SEARCH_BUDGET,
+};
 
 // TODO: delete this helper logging code at some point
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
+#[allow(dead_code)]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
+#[cfg(feature = "wasm")]
+#[allow(unused_macros)]
 macro_rules! console_log {
     ($($t:tt)*) => (unsafe { log(&format_args!($($t)*).to_string()) })
 }
 
+/// A set of the digits a cell could be, with bit `v` standing for the digit `v + 1`.
+pub type Candidates = u32;
+
+/// The symbols for the digits in one character, so up to 32 digits can be written.
+const SYMBOLS: &str = "123456789ABCDEFGHIJKLMNOPQRSTUVW";
+
+/// Every digit of a puzzle with `size` digits.
+pub(crate) fn all_candidates(size: usize) -> Candidates {
+    Candidates::MAX.checked_shr(Candidates::BITS - size as u32).unwrap_or(0)
+}
+
+/// The symbol for a digit (from 1) as written in a grid.
+pub fn symbol(digit: usize) -> char {
+    SYMBOLS.as_bytes()[digit - 1] as char
+}
+
+/// The digit written with a symbol (letters in either case).
+pub(crate) fn symbol_digit(ch: char) -> Option<usize> {
+    SYMBOLS.find(ch.to_ascii_uppercase()).map(|ix| ix + 1)
+}
+
 const FIXED_MASK: u8 = 0b10000000;
 const COLOR_MASK: u8 = 0b00111111;
 
+/// The number of rows and columns in each box: as close to square as the size allows (e.g.
+/// 3x3 for a 9x9 and 2x3 for a 6x6), with the longer side across unless `tall`.
+///
+/// Sizes with no such split (e.g. 7) get a single row for each box.
+pub(crate) fn box_shape(size: usize, tall: bool) -> (usize, usize) {
+    let rows = (1..=size).take_while(|r| r * r <= size).filter(|r| size.is_multiple_of(*r)).last().unwrap_or(1);
+    if tall {
+        (size / rows, rows)
+    } else {
+        (rows, size / rows)
+    }
+}
+
 fn generate_boxes(size: usize) -> Vec<Vec<usize>> {
-    // TODO: this should be able to generate colors too?
-    let box_size = (size as f64).sqrt().floor() as usize;
+    generate_shaped_boxes(size, box_shape(size, false))
+}
+
+/// The rows, columns and boxes of a grid with boxes of `box_rows` by `box_cols` cells.
+fn generate_shaped_boxes(size: usize, (box_rows, box_cols): (usize, usize)) -> Vec<Vec<usize>> {
     let mut rows = Vec::with_capacity(size);
     let mut cols = Vec::with_capacity(size);
     let mut boxes = Vec::with_capacity(size);
@@ -32,33 +104,85 @@ fn generate_boxes(size: usize) -> Vec<Vec<usize>> {
         rows.push(row + 1);
         let col: usize = ix % size;
         cols.push(col + 1);
-        if size == 6  || size == 8{
-            // TODO: test this
-            boxes.push(2 * (row / 2) + (col / (size / 2)));
-        } else {
-            let box_idx: usize = box_size * (row / box_size) + (col / box_size);
-            boxes.push(box_idx + 1);
-        }
+        let box_idx: usize = (size / box_cols) * (row / box_rows) + (col / box_cols);
+        boxes.push(box_idx + 1);
     }
     vec![rows, cols, boxes]
 }
 
-#[wasm_bindgen]
+/// Add a region to the first region set of `kind` that has room for all its cells (or a new
+/// set if none do), unless that set already has the same region.
+fn add_region(sets: &mut Vec<Vec<usize>>, kinds: &mut Vec<usize>, kind: usize, cells: &[usize]) {
+    let existing = sets.iter().zip(kinds.iter()).any(|(set, k)| {
+        *k == kind
+            && set[cells[0]] != 0
+            && set.iter().filter(|r| **r == set[cells[0]]).count() == cells.len()
+            && cells.iter().all(|c| set[*c] == set[cells[0]])
+    });
+    if existing {
+        return;
+    }
+    let free = (0..sets.len()).find(|i| kinds[*i] == kind && cells.iter().all(|c| sets[*i][*c] == 0));
+    let ix = free.unwrap_or_else(|| {
+        sets.push(vec![0; sets[0].len()]);
+        kinds.push(kind);
+        sets.len() - 1
+    });
+    let id = sets[ix].iter().max().unwrap_or(&0) + 1;
+    for cell in cells {
+        sets[ix][*cell] = id;
+    }
+}
+
+/// The region sets for several square grids that overlap, given where each grid's top left
+/// cell sits in the bounding grid and where every cell sits in it (see `Puzzle::positions`).
+///
+/// The first three sets are still the rows, columns and boxes, with more row and column sets
+/// for the cells that are in several grids.
+fn generate_overlapping_boxes(
+    size: usize,
+    origins: &[(usize, usize)],
+    width: usize,
+    positions: &[usize],
+) -> Vec<Vec<usize>> {
+    let (box_rows, box_cols) = box_shape(size, false);
+    let across = size / box_cols;
+    let cell = |row: usize, col: usize| positions.binary_search(&(row * width + col)).unwrap();
+    let mut sets = vec![vec![0; positions.len()]; 3];
+    let mut kinds = vec![0, 1, 2];
+    for (top, left) in origins {
+        for i in 0..size {
+            let row: Vec<usize> = (0..size).map(|j| cell(top + i, left + j)).collect();
+            add_region(&mut sets, &mut kinds, 0, &row);
+            let col: Vec<usize> = (0..size).map(|j| cell(top + j, left + i)).collect();
+            add_region(&mut sets, &mut kinds, 1, &col);
+            let (box_top, box_left) = (top + box_rows * (i / across), left + box_cols * (i % across));
+            let square: Vec<usize> =
+                (0..size).map(|j| cell(box_top + j / box_cols, box_left + j % box_cols)).collect();
+            add_region(&mut sets, &mut kinds, 2, &square);
+        }
+    }
+    sets
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Copy, Clone, Debug)]
 pub enum AutoPencil {
-   Never = "never",
-   OnlyRemove = "onlyremove",
-   Always = "always",
-   Snyder = "synder",
+   Never,
+   OnlyRemove,
+   Always,
+   Snyder,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone)]
 pub struct Puzzle {
     /// The candidates and solutions for the grid
-    values: Vec<u16>,
-    /// How many rows or cols the grid has.
+    values: Vec<Candidates>,
+    /// How many digits the puzzle uses (and rows or cols each grid has).
     pub size: usize,
+    /// Where the cells sit and which ones are next to each other.
+    geometry: Geometry,
     /// Cached values for the rows, cols, and boxes.
     boxes: Vec<Vec<usize>>,
     /// Stores in a bit array if values are fixed (preset) and their color.
@@ -68,27 +192,27 @@ pub struct Puzzle {
     /// If each cell is "solved".
     solved: Vec<bool>,
     /// All the previous board states (values and solved)
-    history: Vec<(usize, u16, bool)>,
+    history: Vec<(usize, Candidates, bool)>,
+    /// Rules the puzzle has on top of the regions in `boxes`.
+    constraints: Vec<Rc<dyn Constraint>>,
+    /// The cells each cell can't share a digit with under any rule, worked out again whenever
+    /// the regions or rules change (and shared between copies of the board).
+    peers: Rc<Vec<Vec<usize>>>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Puzzle {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new() -> Puzzle {
-        let size = 9;
-        Puzzle {
-            size,
-            boxes: generate_boxes(size),
-            types: vec![0; size * size],
-            truths: vec![0; size * size],
-            values: vec![0; size * size],
-            solved: vec![false; size * size],
-            history: Vec::new(),
-        }
+        Self::raw_from_grid(&[0; 81])
     }
 
     pub fn raw_from_grid(grid: &[u8]) -> Puzzle {
         let size = (grid.len() as f64).sqrt().floor() as usize;
+        Self::raw_from_cells(grid, size, Geometry::square(size), generate_boxes(size))
+    }
+
+    fn raw_from_cells(grid: &[u8], size: usize, geometry: Geometry, boxes: Vec<Vec<usize>>) -> Puzzle {
         let mut types = Vec::with_capacity(grid.len());
         let mut values = Vec::with_capacity(grid.len());
         let mut solved = Vec::with_capacity(grid.len());
@@ -103,23 +227,306 @@ impl Puzzle {
                 solved.push(true);
             }
         }
-        Puzzle {
+        let mut puzzle = Puzzle {
             size,
-            boxes: generate_boxes(size),
+            geometry,
+            boxes,
             types,
             truths: grid.to_vec(),
             values,
             solved,
             history: Vec::new(),
-        }
+            constraints: Vec::new(),
+            peers: Rc::new(Vec::new()),
+        };
+        puzzle.index_units();
+        puzzle
     }
 
-    pub fn from_grid(grid: &[u8]) -> Result<Puzzle, String> {
+    /// A puzzle from its givens (0 for blanks), which need to have a single solution. Givens
+    /// with several solutions are a `MultipleSolutions` error with cells that tell two of them
+    /// apart.
+    pub fn from_grid(grid: &[u8]) -> Result<Puzzle, QuadrataError> {
         let mut puzzle = Self::raw_from_grid(grid);
         puzzle.truths = solve(&puzzle)?;
         Ok(puzzle)
     }
 
+    /// The cells whose values differ between the solutions of an ambiguous puzzle.
+    ///
+    /// Variant puzzles are searched with a limited number of guesses, so one that's too open
+    /// (like an empty anti-knight grid) is a `SearchLimit` error rather than a long wait.
+    pub fn ambiguous_cells(&self) -> Result<Vec<usize>, QuadrataError> {
+        Ok(ambiguous_cells(&bounded_solutions(self, 64, SEARCH_BUDGET)?))
+    }
+
+    /// A grid of extra givens (0 for no change) that would give the puzzle a unique solution,
+    /// with the same limit on variant puzzles as `ambiguous_cells`.
+    pub fn suggest_givens(&self) -> Result<Vec<u8>, QuadrataError> {
+        let mut grid = vec![0; self.values.len()];
+        for (cell, value) in suggest_givens(self)? {
+            grid[cell] = value;
+        }
+        Ok(grid)
+    }
+
+    /// If every given is needed for the puzzle to have a unique solution.
+    pub fn is_minimal(&self) -> Result<bool, QuadrataError> {
+        is_minimal(self)
+    }
+
+    /// Remove givens that aren't needed for a unique solution and return the cleared cells.
+    pub fn minimize(&mut self) -> Result<Vec<usize>, QuadrataError> {
+        let removed = minimize(self)?;
+        for i in &removed {
+            self.types[*i] &= !FIXED_MASK;
+            self.values[*i] = 0;
+            self.solved[*i] = false;
+        }
+        Ok(removed)
+    }
+
+    /// How hard the puzzle is, as the difficulty of the hardest strategy needed to solve it.
+    pub fn difficulty(&self) -> u32 {
+        rate(self)
+    }
+
+    /// The puzzle's solution (or 0s if it hasn't been solved).
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn solution(&self) -> Vec<u8> {
+        self.truths.clone()
+    }
+
+    /// Replace the square boxes with irregular regions (jigsaw sudoku), given as a region id
+    /// from 1 to `size` for every cell.
+    pub fn set_regions(&mut self, regions: Vec<usize>) -> Result<(), QuadrataError> {
+        if regions.len() != self.values.len() {
+            return Err(QuadrataError::InvalidLength {
+                expected: self.values.len(),
+                found: regions.len(),
+            });
+        }
+        let mut region_sizes = vec![0; self.size + 1];
+        for region in &regions {
+            if *region == 0 || *region > self.size {
+                let cells = regions.iter().filter(|r| *r == region).count();
+                return Err(QuadrataError::InvalidRegion { region: *region, cells });
+            }
+            region_sizes[*region] += 1;
+        }
+        for (region, cells) in region_sizes.into_iter().enumerate().skip(1) {
+            if cells != self.size {
+                return Err(QuadrataError::InvalidRegion { region, cells });
+            }
+        }
+        self.boxes[2] = regions;
+        self.index_units();
+        Ok(())
+    }
+
+    /// Switch the boxes of a single grid with rectangular boxes between wide ones (the
+    /// default, e.g. 2 rows by 3 columns in a 6x6) and tall ones (3 rows by 2 columns).
+    ///
+    /// Grids that overlap (like a Samurai) keep the boxes of their layout, so changing them is
+    /// an `UnsupportedConstraints` error.
+    pub fn set_tall_boxes(&mut self, tall: bool) -> Result<(), QuadrataError> {
+        if self.values.len() != self.size * self.size {
+            return Err(QuadrataError::UnsupportedConstraints);
+        }
+        self.boxes[2] = generate_shaped_boxes(self.size, box_shape(self.size, tall)).remove(2);
+        self.index_units();
+        Ok(())
+    }
+
+    /// Join each edge of the grid to the opposite one (a torus), so neighbors and chess moves
+    /// carry on around the edges. Irregular regions that wrap around are set as usual with
+    /// `set_regions`.
+    pub fn set_torus(&mut self, wraps: bool) {
+        self.geometry.wraps = wraps;
+        self.index_peers();
+    }
+
+    /// Add the two main diagonals as regions (X-Sudoku), of the top left grid if several
+    /// overlap.
+    ///
+    /// Each diagonal is its own region set since the middle cell of an odd-sized grid is on
+    /// both; cells off a diagonal get the region id 0 so they're skipped.
+    pub fn add_diagonals(&mut self) {
+        self.add_diagonal(true);
+        self.add_diagonal(false);
+    }
+
+    /// Add one main diagonal as a region, going down from the top left corner if `down` or
+    /// up from the bottom left corner otherwise.
+    pub fn add_diagonal(&mut self, down: bool) {
+        let mut diagonal = vec![0; self.values.len()];
+        for i in 0..self.size as isize {
+            let row = if down { i } else { self.size as isize - 1 - i };
+            diagonal[self.cell_at(row, i).unwrap()] = 1;
+        }
+        self.boxes.push(diagonal);
+        self.index_units();
+    }
+
+    /// Add the extra boxes of Hyper Sudoku (Windoku) that sit one cell in from each of the
+    /// regular boxes' corners, e.g. the four 3x3 windows of a 9x9 grid.
+    pub fn add_windows(&mut self) {
+        let (box_rows, box_cols) = box_shape(self.size, false);
+        let mut windows = vec![0; self.values.len()];
+        let row_starts: Vec<usize> = (0..self.size / box_rows - 1).map(|k| 1 + k * (box_rows + 1)).collect();
+        let col_starts: Vec<usize> = (0..self.size / box_cols - 1).map(|k| 1 + k * (box_cols + 1)).collect();
+        for (wr, row) in row_starts.iter().enumerate() {
+            for (wc, col) in col_starts.iter().enumerate() {
+                for i in 0..self.size {
+                    let cell = self.cell_at((row + i / box_cols) as isize, (col + i % box_cols) as isize);
+                    windows[cell.unwrap()] = wr * col_starts.len() + wc + 1;
+                }
+            }
+        }
+        self.boxes.push(windows);
+        self.index_units();
+    }
+
+    /// Add disjoint groups: the cells in the same position of every box form a region.
+    ///
+    /// Grids that overlap (like a Samurai) have more boxes than digits so their groups couldn't
+    /// hold each digit once, and are an `UnsupportedConstraints` error.
+    pub fn add_disjoint_groups(&mut self) -> Result<(), QuadrataError> {
+        if self.values.len() != self.size * self.size {
+            return Err(QuadrataError::UnsupportedConstraints);
+        }
+        let mut groups = vec![0; self.values.len()];
+        let mut position = vec![0; self.size + 1];
+        for (cell, region) in self.boxes[2].iter().enumerate() {
+            position[*region] += 1;
+            groups[cell] = position[*region];
+        }
+        self.boxes.push(groups);
+        self.index_units();
+        Ok(())
+    }
+
+    /// Color the cells (Colored Sudoku) with a color from 1 for every cell, or 0 for the cells
+    /// without one. The cells of each color can't repeat a digit, the same as a region, and
+    /// the strategies take every region as holding each digit once so there have to be exactly
+    /// `size` cells of each color.
+    pub fn add_colors(&mut self, colors: Vec<u8>) -> Result<(), QuadrataError> {
+        if colors.len() != self.values.len() {
+            let (expected, found) = (self.values.len(), colors.len());
+            return Err(QuadrataError::InvalidLength { expected, found });
+        }
+        let max = colors.iter().max().copied().unwrap_or(0);
+        for color in 1..=max {
+            let cells = colors.iter().filter(|c| **c == color).count();
+            if cells == 0 {
+                continue;
+            }
+            // colors only get the bits `types` has spare for them
+            if cells != self.size || color > COLOR_MASK {
+                return Err(QuadrataError::InvalidRegion { region: color as usize, cells });
+            }
+        }
+        for (kind, color) in self.types.iter_mut().zip(&colors) {
+            *kind = (*kind & !COLOR_MASK) | color;
+        }
+        self.boxes.push(colors.into_iter().map(usize::from).collect());
+        self.index_units();
+        Ok(())
+    }
+
+    /// The color of each cell (0 for none), see `add_colors`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn colors(&self) -> Vec<u8> {
+        self.types.iter().map(|t| t & COLOR_MASK).collect()
+    }
+
+    /// Stop cells a chess knight's move apart from having the same digit.
+    pub fn add_anti_knight(&mut self) {
+        self.add_constraint(AntiKnight);
+    }
+
+    /// Stop cells a chess king's move apart from having the same digit.
+    pub fn add_anti_king(&mut self) {
+        self.add_constraint(AntiKing);
+    }
+
+    /// Shade cells that can only hold even digits.
+    pub fn add_even(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Parity { cells, even: true });
+    }
+
+    /// Shade cells that can only hold odd digits.
+    pub fn add_odd(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Parity { cells, even: false });
+    }
+
+    /// Add a greater-than sign between neighboring cells pointing from `greater` to `lesser`.
+    pub fn add_greater(&mut self, greater: usize, lesser: usize) {
+        let marks = vec![(greater, lesser, EdgeKind::Greater)];
+        self.add_constraint(Edges { marks, negative: Vec::new() });
+    }
+
+    /// Stop orthogonally neighboring cells from having consecutive digits.
+    pub fn add_non_consecutive(&mut self) {
+        self.add_constraint(Edges::non_consecutive());
+    }
+
+    /// Add a killer cage whose digits don't repeat and add up to `sum` (or 0 for no total).
+    pub fn add_cage(&mut self, cells: Vec<usize>, sum: u32) {
+        self.add_constraint(Cage { cells, sum });
+    }
+
+    /// Add a region of cells that can't repeat a digit, without having to hold every digit.
+    pub fn add_extra_region(&mut self, cells: Vec<usize>) {
+        self.add_constraint(ExtraRegion { cells });
+    }
+
+    /// Add a thermometer whose digits increase from the bulb in the first cell.
+    pub fn add_thermo(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Thermo { cells });
+    }
+
+    /// Add an arrow whose path adds up to the number in its circle (or pill).
+    pub fn add_arrow(&mut self, circle: Vec<usize>, path: Vec<usize>) {
+        self.add_constraint(Arrow { circle, path });
+    }
+
+    /// Add a German whispers line: neighbors on it differ by at least 5 (in a 9x9).
+    pub fn add_whisper(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Line { kind: LineKind::Whisper, cells });
+    }
+
+    /// Add a renban line: its digits are a set of consecutive numbers in any order.
+    pub fn add_renban(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Line { kind: LineKind::Renban, cells });
+    }
+
+    /// Add a palindrome line that reads the same from either end.
+    pub fn add_palindrome(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Line { kind: LineKind::Palindrome, cells });
+    }
+
+    /// Add a region sum line: its parts in each box add up to the same total.
+    pub fn add_region_sum_line(&mut self, cells: Vec<usize>) {
+        self.add_constraint(Line { kind: LineKind::RegionSum, cells });
+    }
+
+    /// Add a sandwich clue on a row or column: the digits between its 1 and its highest digit
+    /// add up to `sum`.
+    pub fn add_sandwich(&mut self, cells: Vec<usize>, sum: u32) {
+        self.add_constraint(Sandwich { cells, sum });
+    }
+
+    /// Add an X-sum clue: the first of `cells` says how many of them add up to `sum`.
+    pub fn add_x_sum(&mut self, cells: Vec<usize>, sum: u32) {
+        self.add_constraint(XSum { cells, sum });
+    }
+
+    /// Add a little killer clue whose diagonal of `cells` adds up to `sum`.
+    pub fn add_little_killer(&mut self, cells: Vec<usize>, sum: u32) {
+        self.add_constraint(LittleKiller { cells, sum });
+    }
+
     pub fn to_grid(&self) -> Vec<u8> {
         let mut grid = Vec::new();
         for (v, s) in self.values.iter().zip(&self.solved) {
@@ -159,15 +566,14 @@ impl Puzzle {
         }
     }
 
-    #[wasm_bindgen(getter)]
-    pub fn fixed_values(&self) -> Vec<JsValue> {
-        self.types
-            .iter()
-            .map(|x| JsValue::from_bool(x & FIXED_MASK == FIXED_MASK))
-            .collect()
+    /// `fixed_values` for JS, which wasm-bindgen can't hand a `Vec<bool>`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(getter = fixed_values)]
+    pub fn js_fixed_values(&self) -> Vec<JsValue> {
+        self.fixed_values().into_iter().map(JsValue::from_bool).collect()
     }
 
-    #[wasm_bindgen(getter)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
     pub fn values(&self) -> Vec<u8> {
         self.values
             .iter()
@@ -203,14 +609,14 @@ impl Puzzle {
         }
     }
 
-    #[wasm_bindgen(getter)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
     pub fn guesses(&self) -> Vec<String> {
         let mut str_guesses: Vec<String> = Vec::new();
         for v in &self.values {
             let mut str_guess = String::new();
             for i in 0..self.size {
                 if 1 & (v >> i) == 1 {
-                    str_guess.push_str(&format!("{:X}", i + 1));
+                    str_guess.push(symbol(i + 1));
                 }
             }
             str_guesses.push(str_guess);
@@ -229,39 +635,32 @@ impl Puzzle {
             return bad_cells;
         }
 
-        let counts = get_counts(&self, true);
-        for (i, v) in (0..self.values.len()).filter(|i| self.solved[*i]).map(|i| (i, self.values[i].trailing_zeros())) {
-            let mut loop_cyc = 0;
-            for (cons, count) in self.boxes.iter().zip(&counts) {
-                loop_cyc += 1;
-                if count[cons[i] - 1][v as usize] > 1 {
-                    bad_cells.push(i);
-                    break;
-                }
-            }
+        for constraint in self.constraints() {
+            bad_cells.extend(constraint.conflicts(self));
         }
+        bad_cells.sort_unstable();
+        bad_cells.dedup();
         bad_cells
     }
 
     pub fn update_guesses(&mut self, style: AutoPencil) {
-        let updated_guesses = redo_guesses(&self);
+        let updated_guesses = redo_guesses(self);
         match style {
             AutoPencil::Always => {
                 self.values = updated_guesses;
-                return;
             },
             AutoPencil::Snyder => {
                 // TODO: it would be nice if this could take any user edited guesses
                 // into account when updating
-                let mut box_guess_counts = vec![vec![0u8; self.size]; self.size];
+                let box_of = |i: usize| self.geometry.unit_in(2, i).unwrap_or(0);
+                let mut box_guess_counts = vec![vec![0u8; self.size]; self.geometry.n_units()];
                 for i in 0..self.values.len() {
                     if self.solved[i] {
                         continue;
                     }
-                    for val in 0..self.size {
+                    for (val, count) in box_guess_counts[box_of(i)].iter_mut().enumerate() {
                         if (updated_guesses[i] >> val) & 1 == 1 {
-                            let box_idx = self.boxes.get(2).map(|b| b[i] - 1).unwrap_or(0);
-                            box_guess_counts[box_idx][val] += 1;
+                            *count += 1;
                         }
                     }
                 }
@@ -269,10 +668,8 @@ impl Puzzle {
                     if self.solved[i] {
                         continue;
                     }
-                    let box_idx = self.boxes.get(2).map(|b| b[i] - 1).unwrap_or(0);
                     let mut new_value = 0;
-                    for val in 0..self.size {
-                        let val_box_counts = box_guess_counts[box_idx][val];
+                    for (val, &val_box_counts) in box_guess_counts[box_of(i)].iter().enumerate() {
                         if (updated_guesses[i] >> val) & 1 == 1 && val_box_counts > 0 && val_box_counts <= 2 {
                             new_value |= 1 << val;
                         }
@@ -280,9 +677,10 @@ impl Puzzle {
                     self.values[i] = new_value;
                 }
             },
-            AutoPencil::Always => {
-                for i in 0..self.values.len() {
-                    self.values[i] &= updated_guesses[i];
+            AutoPencil::OnlyRemove => {
+                // clear out marks the constraints rule out, but never add any back
+                for (value, updated) in self.values.iter_mut().zip(&updated_guesses) {
+                    *value &= updated;
                 }
             }
             _ => {},
@@ -342,6 +740,103 @@ impl Puzzle {
     }
 }
 
+impl Puzzle {
+    /// Which cells are givens.
+    pub fn fixed_values(&self) -> Vec<bool> {
+        self.types.iter().map(|x| x & FIXED_MASK == FIXED_MASK).collect()
+    }
+
+    /// A puzzle of several `size` by `size` grids that share some of their boxes (like a
+    /// Samurai), with the top left cell of each grid at `origins` (a row and column). `grid`
+    /// has the givens for every spot in the box around all the grids; the spots outside the
+    /// grids are left out of the puzzle.
+    pub fn raw_from_layout(size: usize, origins: &[(usize, usize)], grid: &[u8]) -> Puzzle {
+        let width = origins.iter().map(|o| o.1 + size).max().unwrap_or(0);
+        let height = origins.iter().map(|o| o.0 + size).max().unwrap_or(0);
+        let mut covered = vec![false; width * height];
+        for (top, left) in origins {
+            for i in 0..size * size {
+                covered[(top + i / size) * width + left + i % size] = true;
+            }
+        }
+        let positions: Vec<usize> = (0..width * height).filter(|p| covered[*p]).collect();
+        let givens: Vec<u8> = positions.iter().map(|p| grid.get(*p).copied().unwrap_or(0)).collect();
+        let boxes = generate_overlapping_boxes(size, origins, width, &positions);
+        Self::raw_from_cells(&givens, size, Geometry::new(width, positions), boxes)
+    }
+
+    /// Where the cells sit and which ones are next to each other.
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Bring the units of the geometry up to date after changing the regions.
+    fn index_units(&mut self) {
+        self.geometry.index_units(&self.boxes);
+        self.index_peers();
+    }
+
+    /// Bring the peers of every cell up to date after changing the regions or rules.
+    fn index_peers(&mut self) {
+        let peers = (0..self.values.len())
+            .map(|cell| {
+                let mut peers: Vec<usize> =
+                    self.constraints().flat_map(|c| c.peers(self, cell)).collect();
+                peers.sort_unstable();
+                peers.dedup();
+                peers
+            })
+            .collect();
+        self.peers = Rc::new(peers);
+    }
+
+    /// The row and column of a cell in the grid.
+    pub fn row_col(&self, cell: usize) -> (usize, usize) {
+        self.geometry.row_col(cell)
+    }
+
+    /// The cell at a row and column of the grid, if there's one there.
+    pub fn cell_at(&self, row: isize, col: isize) -> Option<usize> {
+        self.geometry.cell_at(row, col)
+    }
+
+    /// How many rows the grid has.
+    pub fn height(&self) -> usize {
+        self.geometry.height()
+    }
+
+    /// Add a rule the puzzle's digits have to follow on top of its regions.
+    pub fn add_constraint(&mut self, constraint: impl Constraint) {
+        self.constraints.push(Rc::new(constraint));
+        self.index_peers();
+    }
+
+    /// All the rules of the puzzle, starting with its regions.
+    pub fn constraints(&self) -> impl Iterator<Item = &dyn Constraint> {
+        std::iter::once(&Regions as &dyn Constraint)
+            .chain(self.constraints.iter().map(|c| c.as_ref()))
+    }
+
+    /// The cells that can't share a digit with `cell` under any of the puzzle's rules.
+    pub fn peers(&self, cell: usize) -> &[usize] {
+        &self.peers[cell]
+    }
+
+    /// The constraints of one particular type.
+    pub fn constraints_of<T: Constraint>(&self) -> impl Iterator<Item = &T> {
+        self.constraints
+            .iter()
+            .filter_map(|c| (c.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+}
+
+impl Default for Puzzle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn init() {
     #[cfg(feature = "console_error_panic_hook")]
@@ -351,6 +846,7 @@ pub fn init() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solver::solutions;
 
     #[test]
     fn test_to_and_from_grid() {
@@ -359,4 +855,207 @@ mod tests {
         let new_grid = puzzle.to_grid();
         assert_eq!(grid, new_grid);
     }
+
+    #[test]
+    fn test_update_guesses() {
+        let grid = vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4];
+        let mut puzzle = Puzzle::raw_from_grid(&grid);
+        // marks 1 and 2 in the first cell, where the 1 is already in the row
+        puzzle.set_guess(0, 1);
+        puzzle.set_guess(0, 2);
+        let mut only_remove = puzzle.clone();
+        only_remove.update_guesses(AutoPencil::OnlyRemove);
+        assert_eq!(&only_remove.values[..2], &[0b0010, 0]);
+        puzzle.update_guesses(AutoPencil::Always);
+        assert_eq!(&puzzle.values[..2], &[0b1110, 0b1110]);
+    }
+
+    /// A test rule that the first cell has to be odd.
+    struct FirstOdd;
+
+    impl Constraint for FirstOdd {
+        fn prune(&self, board: &Puzzle, values: &mut [Candidates]) {
+            if !board.solved[0] {
+                values[0] &= 0b0101;
+            }
+        }
+
+        fn conflicts(&self, board: &Puzzle) -> Vec<usize> {
+            if board.solved[0] && board.values[0] & 0b0101 == 0 {
+                vec![0]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    #[test]
+    fn test_constraints() {
+        let grid = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 3, 3, 4, 2, 1];
+        let mut puzzle = Puzzle::raw_from_grid(&grid);
+        assert!(matches!(solve(&puzzle), Err(QuadrataError::MultipleSolutions { .. })));
+        puzzle.add_constraint(FirstOdd);
+        assert_eq!(solve(&puzzle), Ok(vec![1, 2, 3, 4, 4, 3, 1, 2, 2, 1, 4, 3, 3, 4, 2, 1]));
+        assert_eq!(redo_guesses(&puzzle)[0], 1);
+
+        puzzle.set_value(0, 4);
+        assert_eq!(puzzle.verify(false), vec![0]);
+    }
+
+    #[test]
+    fn test_diagonals() {
+        let grid = vec![1, 2, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0];
+        assert!(matches!(Puzzle::from_grid(&grid), Err(QuadrataError::MultipleSolutions { .. })));
+        let mut puzzle = Puzzle::raw_from_grid(&grid);
+        puzzle.add_diagonals();
+        assert_eq!(puzzle.boxes.len(), 5);
+        assert_eq!(puzzle.boxes[3], vec![1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(solve(&puzzle), Ok(vec![1, 2, 3, 4, 3, 4, 1, 2, 4, 3, 2, 1, 2, 1, 4, 3]));
+
+        puzzle.set_value(5, 1);
+        assert_eq!(puzzle.verify(false), vec![0, 5]);
+    }
+
+    #[test]
+    fn test_set_regions() {
+        let grid = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 3, 0, 1];
+        let mut puzzle = Puzzle::raw_from_grid(&grid);
+        puzzle.set_regions(vec![1, 1, 1, 2, 3, 1, 2, 2, 3, 3, 4, 2, 3, 4, 4, 4]).unwrap();
+        assert_eq!(solve(&puzzle), Ok(vec![1, 2, 3, 4, 2, 4, 1, 3, 3, 1, 4, 2, 4, 3, 2, 1]));
+
+        assert_eq!(
+            puzzle.set_regions(vec![1, 1, 1, 1, 1, 2, 2, 2, 3, 3, 3, 2, 3, 4, 4, 4]),
+            Err(QuadrataError::InvalidRegion { region: 1, cells: 5 }),
+        );
+        assert_eq!(
+            puzzle.set_regions(vec![1, 1, 1, 2, 3, 1, 2, 2, 3, 3, 5, 2, 3, 4, 4, 4]),
+            Err(QuadrataError::InvalidRegion { region: 5, cells: 1 }),
+        );
+        assert_eq!(
+            puzzle.set_regions(vec![1, 1]),
+            Err(QuadrataError::InvalidLength { expected: 16, found: 2 }),
+        );
+    }
+
+    #[test]
+    fn test_extra_regions() {
+        let mut puzzle = Puzzle::new();
+        puzzle.add_windows();
+        let windows = &puzzle.boxes[3];
+        assert_eq!(&windows[9..18], &[0, 1, 1, 1, 0, 2, 2, 2, 0]);
+        assert_eq!(&windows[45..54], &[0, 3, 3, 3, 0, 4, 4, 4, 0]);
+        assert_eq!(windows.iter().filter(|w| **w == 4).count(), 9);
+
+        puzzle.add_disjoint_groups().unwrap();
+        let groups = &puzzle.boxes[4];
+        assert_eq!(&groups[..9], &[1, 2, 3, 1, 2, 3, 1, 2, 3]);
+        assert_eq!(&groups[72..81], &[7, 8, 9, 7, 8, 9, 7, 8, 9]);
+
+        // any solution has to have every digit once in each window and group
+        let solution = solutions(&puzzle, 1).unwrap().remove(0);
+        for cons in &puzzle.boxes[3..] {
+            for region in 1..=9 {
+                let mut digits: Vec<u8> = (0..81).filter(|i| cons[*i] == region).map(|i| solution[i]).collect();
+                digits.sort();
+                assert!(digits.is_empty() || digits == vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlapping_grids() {
+        // two grids sharing the bottom right box of the first one
+        let puzzle = Puzzle::raw_from_layout(9, &[(0, 0), (6, 6)], &[0; 225]);
+        assert_eq!((puzzle.geometry.width, puzzle.height(), puzzle.values.len()), (15, 15, 153));
+        assert_eq!(puzzle.cell_at(0, 9), None);
+        let shared = puzzle.cell_at(7, 7).unwrap();
+        assert_eq!(puzzle.row_col(shared), (7, 7));
+        // the shared box is one region but its cells are in two rows and two columns
+        assert_eq!(puzzle.boxes.len(), 5);
+        assert_eq!(puzzle.boxes[2].iter().max(), Some(&17));
+        assert!(puzzle.boxes.iter().all(|cons| cons[shared] != 0));
+
+        let mut solved = puzzle.clone();
+        let solution = solutions(&puzzle, 1).unwrap().remove(0);
+        for (i, v) in solution.iter().enumerate() {
+            solved.values[i] = 1 << (v - 1);
+            solved.solved[i] = true;
+        }
+        assert_eq!(solved.verify(false), Vec::<usize>::new());
+        for row in 6..15 {
+            let digits: Vec<u8> = (6..15).map(|c| solution[puzzle.cell_at(row, c).unwrap()]).collect();
+            assert_eq!(digits.iter().map(|d| 1 << d).sum::<u32>(), 0b1111111110);
+        }
+    }
+
+    #[test]
+    fn test_large_candidates() {
+        assert_eq!(all_candidates(9), 0b111111111);
+        assert_eq!(all_candidates(32), Candidates::MAX);
+        let mut puzzle = Puzzle::raw_from_grid(&[0; 625]);
+        puzzle.update_guesses(AutoPencil::Always);
+        assert_eq!(puzzle.guesses()[0], "123456789ABCDEFGHIJKLMNOP");
+        puzzle.set_value(1, 25);
+        puzzle.update_guesses(AutoPencil::Always);
+        assert_eq!(puzzle.guesses()[0], "123456789ABCDEFGHIJKLMNO");
+    }
+
+    #[test]
+    fn test_box_shapes() {
+        assert_eq!(box_shape(9, false), (3, 3));
+        assert_eq!(box_shape(6, false), (2, 3));
+        assert_eq!(box_shape(6, true), (3, 2));
+        assert_eq!(box_shape(8, false), (2, 4));
+        assert_eq!(box_shape(10, false), (2, 5));
+        assert_eq!(box_shape(12, true), (4, 3));
+        assert_eq!(box_shape(7, false), (1, 7));
+
+        for size in [6, 8, 10, 12] {
+            let boxes = &generate_boxes(size)[2];
+            for region in 1..=size {
+                assert_eq!(boxes.iter().filter(|b| **b == region).count(), size);
+            }
+        }
+        let mut puzzle = Puzzle::raw_from_grid(&[0; 144]);
+        assert_eq!(&puzzle.boxes[2][..12], &[1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]);
+        assert_eq!(puzzle.boxes[2][36], 4);
+        puzzle.set_tall_boxes(true).unwrap();
+        assert_eq!(&puzzle.boxes[2][..12], &[1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4]);
+        assert_eq!(puzzle.boxes[2][48], 5);
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut puzzle = Puzzle::raw_from_grid(&[0; 16]);
+        let colors = vec![1, 2, 0, 0, 0, 0, 1, 2, 2, 0, 0, 1, 0, 1, 2, 0];
+        puzzle.add_colors(colors.clone()).unwrap();
+        assert_eq!(puzzle.colors(), colors);
+        // the colors sit alongside the fixed bit
+        puzzle.types[0] |= FIXED_MASK;
+        assert_eq!(puzzle.colors(), colors);
+        assert_eq!(puzzle.boxes.len(), 4);
+        // r1c1 and r4c2 only share a color
+        assert_eq!(puzzle.geometry.peers(0), &[1, 2, 3, 4, 5, 6, 8, 11, 12, 13]);
+
+        assert_eq!(
+            puzzle.add_colors(vec![1; 4]).err(),
+            Some(QuadrataError::InvalidLength { expected: 16, found: 4 }),
+        );
+        assert_eq!(
+            puzzle.add_colors(vec![64; 16]).err(),
+            Some(QuadrataError::InvalidRegion { region: 64, cells: 16 }),
+        );
+
+        // a color that doesn't fill a region would be taken for one by e.g. hidden singles
+        let mut puzzle =
+            from_81("016400000200009000400000062070230100100000003003087040960000005000800007000006820")
+                .unwrap();
+        let mut colors = vec![0; 81];
+        colors[0] = 1;
+        colors[4] = 1;
+        assert_eq!(
+            puzzle.add_colors(colors).err(),
+            Some(QuadrataError::InvalidRegion { region: 1, cells: 2 }),
+        );
+    }
 }
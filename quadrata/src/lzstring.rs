@@ -0,0 +1,230 @@
+use std::collections::{HashMap, HashSet};
+
+/// The alphabet of the base64 form of LZ-string compression (`compressToBase64` in the JS
+/// library), which puzzle sites use to fit a puzzle into a link.
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/// Reads the bits of base64 text starting from the high bit of each character.
+struct BitReader {
+    values: Vec<u32>,
+    index: usize,
+    value: u32,
+    position: u32,
+}
+
+impl BitReader {
+    fn new(text: &str) -> BitReader {
+        // anything outside the alphabet (like padding) reads as no bits set, the same as in JS
+        let values: Vec<u32> = text
+            .bytes()
+            .map(|b| BASE64.iter().position(|c| *c == b).unwrap_or(0) as u32)
+            .collect();
+        let value = values.first().copied().unwrap_or(0);
+        BitReader { values, index: 1, value, position: 32 }
+    }
+
+    /// Read an `n` bit number with the lowest bit first.
+    fn read(&mut self, n: u32) -> u32 {
+        let mut bits = 0;
+        for power in 0..n {
+            if self.value & self.position != 0 {
+                bits |= 1 << power;
+            }
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = 32;
+                self.value = self.values.get(self.index).copied().unwrap_or(0);
+                self.index += 1;
+            }
+        }
+        bits
+    }
+
+    fn finished(&self) -> bool {
+        self.index > self.values.len()
+    }
+}
+
+/// Writes bits as base64 text, filling each character from its high bit.
+struct BitWriter {
+    text: String,
+    value: usize,
+    position: u32,
+}
+
+impl BitWriter {
+    /// Write an `n` bit number with the lowest bit first.
+    fn write(&mut self, mut bits: u32, n: u32) {
+        for _ in 0..n {
+            self.value = (self.value << 1) | (bits & 1) as usize;
+            self.position += 1;
+            if self.position == 6 {
+                self.text.push(BASE64[self.value] as char);
+                (self.value, self.position) = (0, 0);
+            }
+            bits >>= 1;
+        }
+    }
+}
+
+/// Undo `compressToBase64`, or `None` if the text isn't valid compressed data.
+pub fn decompress_from_base64(text: &str) -> Option<String> {
+    let mut reader = BitReader::new(text);
+    // the first three codes are taken by the markers for a new 8 or 16 bit character and the end
+    let mut dictionary: Vec<Vec<u16>> = vec![Vec::new(); 3];
+    let mut enlarge_in = 4;
+    let mut n_bits = 3;
+
+    let first = match reader.read(2) {
+        0 => reader.read(8),
+        1 => reader.read(16),
+        _ => return Some(String::new()),
+    };
+    let mut word = vec![first as u16];
+    dictionary.push(word.clone());
+    let mut result = word.clone();
+    loop {
+        if reader.finished() {
+            return None;
+        }
+        let mut code = reader.read(n_bits) as usize;
+        match code {
+            0 | 1 => {
+                let c = reader.read(if code == 0 { 8 } else { 16 });
+                dictionary.push(vec![c as u16]);
+                code = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return String::from_utf16(&result).ok(),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << n_bits;
+            n_bits += 1;
+        }
+        let entry = match dictionary.get(code) {
+            Some(entry) => entry.clone(),
+            // the one code that can be used before it's added: the last word plus its own start
+            None if code == dictionary.len() => [&word[..], &word[..1]].concat(),
+            None => return None,
+        };
+        result.extend(&entry);
+        dictionary.push([&word[..], &entry[..1]].concat());
+        enlarge_in -= 1;
+        word = entry;
+        if enlarge_in == 0 {
+            enlarge_in = 1 << n_bits;
+            n_bits += 1;
+        }
+    }
+}
+
+/// Undo `compressToBase64` on data taken from a link, which may have had its `+`, `/` and
+/// `=` escaped (or a `+` read back as a space) along the way.
+pub fn decompress_from_link(data: &str) -> Option<String> {
+    let data = data.replace("%2B", "+").replace("%2F", "/").replace("%3D", "=").replace(' ', "+");
+    decompress_from_base64(&data)
+}
+
+/// The state of `compress_to_base64` as it goes through the text.
+struct Compressor {
+    writer: BitWriter,
+    dictionary: HashMap<Vec<u16>, u32>,
+    /// Characters in the dictionary that haven't been written out in full yet.
+    to_create: HashSet<u16>,
+    enlarge_in: u32,
+    n_bits: u32,
+}
+
+impl Compressor {
+    /// The codes get a bit longer once there are enough of them.
+    fn count_code(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.n_bits;
+            self.n_bits += 1;
+        }
+    }
+
+    fn emit(&mut self, word: &[u16]) {
+        if word.len() == 1 && self.to_create.remove(&word[0]) {
+            let (marker, width) = if word[0] < 256 { (0, 8) } else { (1, 16) };
+            self.writer.write(marker, self.n_bits);
+            self.writer.write(word[0] as u32, width);
+            self.count_code();
+        } else {
+            self.writer.write(self.dictionary[word], self.n_bits);
+        }
+        self.count_code();
+    }
+}
+
+/// Compress text the way `compressToBase64` does, so the result can go in a link.
+pub fn compress_to_base64(text: &str) -> String {
+    let mut compressor = Compressor {
+        writer: BitWriter { text: String::new(), value: 0, position: 0 },
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        enlarge_in: 2,
+        n_bits: 2,
+    };
+    // the first three codes are taken by the markers for a new 8 or 16 bit character and the end
+    let mut next_code = 3;
+    let mut word: Vec<u16> = Vec::new();
+    for c in text.encode_utf16() {
+        if !compressor.dictionary.contains_key(&[c][..]) {
+            compressor.dictionary.insert(vec![c], next_code);
+            compressor.to_create.insert(c);
+            next_code += 1;
+        }
+        let joined = [&word[..], &[c]].concat();
+        if compressor.dictionary.contains_key(&joined) {
+            word = joined;
+        } else {
+            compressor.emit(&word);
+            compressor.dictionary.insert(joined, next_code);
+            next_code += 1;
+            word = vec![c];
+        }
+    }
+    if !word.is_empty() {
+        compressor.emit(&word);
+    }
+    let mut writer = compressor.writer;
+    writer.write(2, compressor.n_bits);
+    // like the JS this always pads with at least one bit, then to whole groups of four
+    loop {
+        writer.write(0, 1);
+        if writer.position == 0 {
+            break;
+        }
+    }
+    while !writer.text.len().is_multiple_of(4) {
+        writer.text.push('=');
+    }
+    writer.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress() {
+        assert_eq!(decompress_from_base64("BIUwNmD2A0AEDukBOYAmBCIA").unwrap(), "Hello, world!");
+        assert_eq!(decompress_from_base64("N4IgzglgXgpiBcAWAvkA").unwrap(), "{\"size\":4}");
+        assert_eq!(decompress_from_base64("IY18WBGw3Q==").unwrap(), "aaaaaaaaaaaaaaaaaaaaaaaaabababab");
+        assert_eq!(decompress_from_base64("N4Igzglg"), None);
+    }
+
+    #[test]
+    fn test_compress() {
+        assert_eq!(compress_to_base64("Hello, world!"), "BIUwNmD2A0AEDukBOYAmBCIA");
+        assert_eq!(compress_to_base64("aaaaaaaaaaaaaaaaaaaaaaaaabababab"), "IY18WBGw3Q==");
+        assert_eq!(compress_to_base64("ab"), "IYIyA===");
+        assert_eq!(compress_to_base64("Grüße ✓ 数独"), "OIJwPw+wpgBIyOQ0A6mgbpyA");
+        for text in ["", "a", "{\"size\":4}", "abcabcabcabcabcabcabc"] {
+            assert_eq!(decompress_from_base64(&compress_to_base64(text)).unwrap(), text);
+        }
+    }
+}
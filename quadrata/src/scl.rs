@@ -0,0 +1,403 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use serde_json::{json, Value};
+
+use crate::lzstring::{compress_to_base64, decompress_from_link};
+use crate::solver::solve;
+use crate::{
+    Arrow, Cage, Candidates, ExtraRegion, Line, LineKind, Puzzle, QuadrataError, Thermo, FIXED_MASK,
+};
+
+/// The color of thermometers and their bulbs.
+const THERMO_COLOR: &str = "#CFCFCF";
+/// The color of arrows and the outline of their circles.
+const ARROW_COLOR: &str = "#A1A1A1";
+/// The outline of the cages that stand for extra regions rather than killer cages.
+const EXTRA_REGION_COLOR: &str = "#7F7FFF";
+/// The colors each kind of line is drawn in. SCL only has the drawing and not the rules, so
+/// this is how the lines are told apart when they're read back.
+const LINE_COLORS: &[(LineKind, &str)] = &[
+    (LineKind::Whisper, "#67F067"),
+    (LineKind::Renban, "#F067F0"),
+    (LineKind::Palindrome, "#C0C0C0"),
+    (LineKind::RegionSum, "#2ECBFF"),
+];
+
+fn invalid(reason: &str) -> QuadrataError {
+    QuadrataError::InvalidFormat(reason.to_string())
+}
+
+/// Read a puzzle in SudokuPad's SCL format, from a link to it (e.g.
+/// `https://sudokupad.app/scl...`), its puzzle id starting with `scl` or the JSON it holds.
+///
+/// Givens, pencil marks, regions, killer cages and arrows come across. Cages without a number
+/// are killer cages with a sum of 0 (no total), or skipped as decoration if they have more
+/// cells than digits. SCL only has the drawing and not the rules, so extra regions,
+/// thermometers and lines are only recognised in the colors `to_scl` draws them in and lines
+/// in any other color are taken as decoration; this reads back what `to_scl` writes but not
+/// lines drawn in other tools. The JSON has to be plain, so puzzles saved in SudokuPad's own
+/// shortened or zipped forms can't be read.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn from_scl(text: &str) -> Result<Puzzle, QuadrataError> {
+    let json = scl_json(text)?;
+    let data: Value = serde_json::from_str(&json).map_err(|e| invalid(&e.to_string()))?;
+    let rows = data["cells"].as_array().ok_or_else(|| invalid("missing cells"))?;
+    let size = rows.len();
+    if !(1..=32).contains(&size) {
+        return Err(QuadrataError::UnsupportedSize(size));
+    }
+    let mut givens = Vec::new();
+    let mut marks: Vec<Candidates> = Vec::new();
+    for row in rows {
+        let row = row.as_array().filter(|r| r.len() == size).ok_or_else(|| invalid("bad cells"))?;
+        for cell in row {
+            givens.push(match number(&cell["value"]) {
+                Some(value) if (1..=size).contains(&(value as usize)) => value as u8,
+                Some(_) => return Err(invalid("bad given")),
+                None => 0,
+            });
+            let digits = cell["pencilMarks"].as_array().map_or(&[][..], |a| &a[..]);
+            let bit = |d: &Value| number(d).filter(|d| (1..=size as u32).contains(d)).map(|d| 1 << (d - 1));
+            let mask = digits.iter().try_fold(0, |mask: Candidates, d| Some(mask | bit(d)?));
+            marks.push(mask.ok_or_else(|| invalid("bad pencil marks"))?);
+        }
+    }
+    let mut puzzle = Puzzle::raw_from_grid(&givens);
+
+    let regions = data["regions"].as_array().map_or(&[][..], |a| &a[..]);
+    if !regions.is_empty() {
+        let mut map = vec![0; size * size];
+        for (id, region) in regions.iter().enumerate() {
+            for cell in cells(region, &puzzle).ok_or_else(|| invalid("bad regions"))? {
+                map[cell] = id + 1;
+            }
+        }
+        puzzle.set_regions(map)?;
+    }
+    for cage in items(&data, "cages") {
+        let cells = cells(&cage["cells"], &puzzle).ok_or_else(|| invalid("bad cages"))?;
+        let outline = cage["outlineC"].as_str().unwrap_or("");
+        match number(&cage["value"]) {
+            Some(sum) => puzzle.add_cage(cells, sum),
+            None if outline.eq_ignore_ascii_case(EXTRA_REGION_COLOR) => puzzle.add_extra_region(cells),
+            None if cells.len() <= size => puzzle.add_cage(cells, 0),
+            // too big to hold distinct digits, so it's only there to draw on
+            None => {}
+        }
+    }
+    for line in items(&data, "lines") {
+        let cells = way_points(line, &puzzle).ok_or_else(|| invalid("bad lines"))?;
+        let color = line["color"].as_str().unwrap_or("");
+        if color.eq_ignore_ascii_case(THERMO_COLOR) {
+            puzzle.add_thermo(cells);
+        } else if let Some((kind, _)) = LINE_COLORS.iter().find(|(_, c)| c.eq_ignore_ascii_case(color)) {
+            puzzle.add_constraint(Line { kind: *kind, cells });
+        }
+    }
+    // the circles of arrows are drawn as rounded shapes over the cells
+    let shapes: Vec<&Value> = items(&data, "overlays")
+        .chain(items(&data, "underlays"))
+        .filter(|s| s["rounded"].as_bool() == Some(true))
+        .collect();
+    for arrow in items(&data, "arrows") {
+        let bad = || invalid("bad arrows");
+        let points = arrow["wayPoints"].as_array().ok_or_else(bad)?;
+        let start = points.first().and_then(point).ok_or_else(bad)?;
+        let circle = match shapes.iter().find_map(|s| covered(s, start, &puzzle)) {
+            Some(circle) => circle,
+            None => vec![point_cell(start, &puzzle).ok_or_else(bad)?],
+        };
+        let path = way_points(arrow, &puzzle).ok_or_else(bad)?;
+        puzzle.add_arrow(circle.clone(), path.into_iter().filter(|c| !circle.contains(c)).collect());
+    }
+    puzzle.truths = solve(&puzzle)?;
+    for (cell, mask) in marks.into_iter().enumerate().filter(|(cell, _)| !puzzle.solved[*cell]) {
+        puzzle.values[cell] = mask;
+    }
+    Ok(puzzle)
+}
+
+/// Write a puzzle as a SudokuPad puzzle id (open it at `https://sudokupad.app/<id>`) with its
+/// givens, pencil marks, regions, cages, extra regions, thermometers, arrows and lines.
+///
+/// Cages without a total and extra regions are both drawn as cages without a number, with
+/// extra regions, thermometers and lines in the colors `from_scl` looks for since SCL has no
+/// way to say what rules they stand for.
+///
+/// Digits entered in the grid aren't part of the puzzle so they're left out. Puzzles with
+/// rules SCL can't draw (like anti-knight, Kropki dots or diagonals) are an
+/// `UnsupportedConstraints` error.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn to_scl(puzzle: &Puzzle) -> Result<String, QuadrataError> {
+    let size = puzzle.size;
+    let geometry = puzzle.geometry();
+    // the rows, columns and boxes of a single square grid
+    let plain = !geometry.wraps && geometry.width == size && geometry.positions.len() == size * size;
+    if !plain || puzzle.boxes.len() > 3 {
+        return Err(QuadrataError::UnsupportedConstraints);
+    }
+    let place = |cell: usize| {
+        let (row, col) = geometry.row_col(cell);
+        json!([row, col])
+    };
+    let center = |cell: usize| {
+        let (row, col) = geometry.row_col(cell);
+        json!([row as f64 + 0.5, col as f64 + 0.5])
+    };
+    let digits = |mask: Candidates| -> Vec<usize> {
+        (1..=size).filter(|d| mask & (1 << (d - 1)) != 0).collect()
+    };
+    let cells: Vec<Value> = (0..size * size)
+        .map(|cell| match puzzle.values[cell] {
+            value if puzzle.types[cell] & FIXED_MASK != 0 => json!({ "value": digits(value)[0] }),
+            value if !puzzle.solved[cell] && value != 0 => json!({ "pencilMarks": digits(value) }),
+            _ => json!({}),
+        })
+        .collect();
+    let rows: Vec<Value> = cells.chunks(size).map(|row| Value::from(row.to_vec())).collect();
+    let mut boxes: Vec<usize> = (0..size * size).filter_map(|c| geometry.unit_in(2, c)).collect();
+    boxes.sort_unstable();
+    boxes.dedup();
+    let regions: Vec<Value> =
+        boxes.iter().map(|b| geometry.cells_in(*b).iter().copied().map(place).collect()).collect();
+
+    let mut cages = Vec::new();
+    let mut lines = Vec::new();
+    let mut arrows = Vec::new();
+    let mut underlays = Vec::new();
+    let mut overlays = Vec::new();
+    for cage in puzzle.constraints_of::<Cage>() {
+        let mut drawn = json!({ "cells": cage.cells.iter().copied().map(place).collect::<Vec<_>>() });
+        if cage.sum != 0 {
+            drawn["value"] = json!(cage.sum.to_string());
+        }
+        cages.push(drawn);
+    }
+    for region in puzzle.constraints_of::<ExtraRegion>() {
+        let cells: Vec<Value> = region.cells.iter().copied().map(place).collect();
+        cages.push(json!({ "cells": cells, "outlineC": EXTRA_REGION_COLOR }));
+    }
+    for thermo in puzzle.constraints_of::<Thermo>() {
+        let way_points: Vec<Value> = thermo.cells.iter().copied().map(center).collect();
+        lines.push(json!({ "wayPoints": way_points, "color": THERMO_COLOR, "thickness": 21 }));
+        if let Some(bulb) = thermo.cells.first() {
+            underlays.push(json!({
+                "center": center(*bulb),
+                "width": 0.85,
+                "height": 0.85,
+                "rounded": true,
+                "backgroundColor": THERMO_COLOR,
+            }));
+        }
+    }
+    for line in puzzle.constraints_of::<Line>() {
+        let color = LINE_COLORS.iter().find(|(k, _)| *k == line.kind).map_or("", |(_, c)| c);
+        let way_points: Vec<Value> = line.cells.iter().copied().map(center).collect();
+        lines.push(json!({ "wayPoints": way_points, "color": color, "thickness": 8 }));
+    }
+    for arrow in puzzle.constraints_of::<Arrow>() {
+        // a pill over several cells is a rounded box around all of them
+        let (rows, cols): (Vec<usize>, Vec<usize>) =
+            arrow.circle.iter().map(|c| geometry.row_col(*c)).unzip();
+        let (Some(top), Some(bottom), Some(left), Some(right)) =
+            (rows.iter().min(), rows.iter().max(), cols.iter().min(), cols.iter().max())
+        else {
+            return Err(QuadrataError::UnsupportedConstraints);
+        };
+        let middle = json!([(top + bottom + 1) as f64 / 2.0, (left + right + 1) as f64 / 2.0]);
+        overlays.push(json!({
+            "center": middle,
+            "width": (right - left) as f64 + 0.85,
+            "height": (bottom - top) as f64 + 0.85,
+            "rounded": true,
+            "borderColor": ARROW_COLOR,
+            "backgroundColor": "#FFFFFF",
+            "thickness": 2,
+        }));
+        let way_points: Vec<Value> =
+            std::iter::once(middle).chain(arrow.path.iter().copied().map(center)).collect();
+        arrows.push(json!({
+            "wayPoints": way_points,
+            "color": ARROW_COLOR,
+            "thickness": 2,
+            "headLength": 0.3,
+        }));
+    }
+    let drawn = puzzle.constraints_of::<Cage>().count()
+        + puzzle.constraints_of::<ExtraRegion>().count()
+        + puzzle.constraints_of::<Thermo>().count()
+        + puzzle.constraints_of::<Line>().count()
+        + puzzle.constraints_of::<Arrow>().count();
+    if drawn != puzzle.constraints.len() {
+        return Err(QuadrataError::UnsupportedConstraints);
+    }
+
+    let data = json!({
+        "cellSize": 64,
+        "cells": rows,
+        "regions": regions,
+        "cages": cages,
+        "lines": lines,
+        "arrows": arrows,
+        "underlays": underlays,
+        "overlays": overlays,
+    });
+    Ok(format!("scl{}", compress_to_base64(&data.to_string())))
+}
+
+/// The JSON of a puzzle from a link, a puzzle id or the JSON itself.
+fn scl_json(text: &str) -> Result<String, QuadrataError> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        return Ok(text.to_string());
+    }
+    // the id comes after the last `/` of the link or in its `puzzleid` parameter
+    let id = text.split_once("puzzleid=").map_or(text, |(_, id)| id);
+    let id = id.find("scl").map(|start| &id[start + 3..]).ok_or_else(|| invalid("not an SCL puzzle"))?;
+    let data = id.split(['&', '#', '?']).next().unwrap_or(id);
+    decompress_from_link(data).ok_or_else(|| invalid("not compressed puzzle data"))
+}
+
+/// The entries of a list in the puzzle, or none if it doesn't have one.
+fn items<'a>(data: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    data[key].as_array().into_iter().flatten()
+}
+
+/// A number that may have been saved as either a number or a string.
+fn number(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// A point on the page as (row, column), where the cell in row r and column c covers r to
+/// r + 1 and c to c + 1.
+fn point(value: &Value) -> Option<(f64, f64)> {
+    match value.as_array()?[..] {
+        [ref row, ref col] => Some((row.as_f64()?, col.as_f64()?)),
+        _ => None,
+    }
+}
+
+fn point_cell((row, col): (f64, f64), puzzle: &Puzzle) -> Option<usize> {
+    puzzle.cell_at(row.floor() as isize, col.floor() as isize)
+}
+
+/// A list of cells written as `[row, column]` from 0.
+fn cells(value: &Value, puzzle: &Puzzle) -> Option<Vec<usize>> {
+    let cell = |(row, col): (f64, f64)| puzzle.cell_at(row as isize, col as isize);
+    value.as_array()?.iter().map(|c| point(c).and_then(cell)).collect()
+}
+
+/// The cells a line or arrow passes through in order.
+fn way_points(line: &Value, puzzle: &Puzzle) -> Option<Vec<usize>> {
+    let points = line["wayPoints"].as_array()?;
+    let mut cells: Vec<usize> =
+        points.iter().map(|p| point_cell(point(p)?, puzzle)).collect::<Option<_>>()?;
+    cells.dedup();
+    Some(cells)
+}
+
+/// The cells whose centers are inside a shape, if the shape covers `at`.
+fn covered(shape: &Value, at: (f64, f64), puzzle: &Puzzle) -> Option<Vec<usize>> {
+    let (row, col) = point(&shape["center"])?;
+    let (half_height, half_width) = (shape["height"].as_f64()? / 2.0, shape["width"].as_f64()? / 2.0);
+    let inside = |(r, c): (f64, f64)| (r - row).abs() <= half_height && (c - col).abs() <= half_width;
+    if !inside(at) {
+        return None;
+    }
+    let cells: Vec<usize> = (0..puzzle.values.len())
+        .filter(|cell| {
+            let (r, c) = puzzle.row_col(*cell);
+            inside((r as f64 + 0.5, c as f64 + 0.5))
+        })
+        .collect();
+    (!cells.is_empty()).then_some(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_variant;
+
+    #[test]
+    fn test_scl_round_trip() {
+        let mut puzzle = from_variant(
+            "
+            0004001000000000
+            thermo r1c1 r1c2 r1c3
+            thermo r4c4 r3c4
+            thermo r3c1 r4c1 r4c2
+            cage 3 r1c1 r1c2
+            arrow r1c4 > r2c3 r3c4
+            palindrome r1c1 r2c2 r4c4
+            ",
+        )
+        .unwrap();
+        puzzle.set_guess(5, 3);
+        puzzle.set_guess(5, 4);
+        puzzle.add_extra_region(vec![0, 1, 2]);
+        puzzle.add_cage(vec![14, 15], 0);
+        let id = to_scl(&puzzle).unwrap();
+        assert!(id.starts_with("scl"));
+        let links = [format!("https://sudokupad.app/{}", id), format!("sudokupad.app/?puzzleid={}", id)];
+        for text in [&id, &links[0], &links[1]] {
+            let copy = from_scl(text).unwrap();
+            assert_eq!(copy.solution(), puzzle.solution());
+            assert_eq!(copy.guesses()[5], "34");
+            assert_eq!(copy.constraints_of::<Thermo>().count(), 3);
+            let (cages, arrows) = (copy.constraints_of::<Cage>(), copy.constraints_of::<Arrow>());
+            assert!(cages.eq(puzzle.constraints_of::<Cage>()));
+            assert!(arrows.eq(puzzle.constraints_of::<Arrow>()));
+            assert!(copy.constraints_of::<Line>().eq(puzzle.constraints_of::<Line>()));
+            assert!(copy.constraints_of::<ExtraRegion>().eq(puzzle.constraints_of::<ExtraRegion>()));
+        }
+
+        // irregular regions go across as they are
+        let jigsaw = crate::from_jigsaw("0000000000020301", "AAABCABBCCDBCDDD").unwrap();
+        assert_eq!(from_scl(&to_scl(&jigsaw).unwrap()).unwrap().solution(), jigsaw.solution());
+
+        let mut knights = Puzzle::raw_from_grid(&[0; 16]);
+        knights.add_anti_knight();
+        assert_eq!(to_scl(&knights).err(), Some(QuadrataError::UnsupportedConstraints));
+    }
+
+    #[test]
+    fn test_from_scl_json() {
+        // the jigsaw from `io` with a cosmetic line and a cage without a number
+        let text = r##"{
+            "cells": [
+                [{}, {}, {}, {}],
+                [{}, {}, {}, {}],
+                [{}, {}, {}, {"value": 2}],
+                [{}, {"value": "3"}, {}, {"value": 1}]
+            ],
+            "regions": [
+                [[0, 0], [0, 1], [0, 2], [1, 1]],
+                [[0, 3], [1, 2], [1, 3], [2, 3]],
+                [[1, 0], [2, 0], [2, 1], [3, 0]],
+                [[2, 2], [3, 1], [3, 2], [3, 3]]
+            ],
+            "cages": [{"cells": [[0, 0], [1, 0]]}],
+            "lines": [{"wayPoints": [[0.5, 0.5], [3.5, 3.5]], "color": "#FF0000"}]
+        }"##;
+        let puzzle = from_scl(text).unwrap();
+        assert_eq!(puzzle.solution(), vec![1, 2, 3, 4, 2, 4, 1, 3, 3, 1, 4, 2, 4, 3, 2, 1]);
+        assert_eq!(puzzle.constraints_of::<Cage>().next().unwrap(), &Cage { cells: vec![0, 4], sum: 0 });
+        assert_eq!(puzzle.constraints_of::<ExtraRegion>().count(), 0);
+        assert_eq!(puzzle.constraints_of::<Line>().count(), 0);
+
+        // a cage around the whole grid can't be a region so it doesn't change the solution
+        let everything: Vec<String> = (0..16).map(|c| format!("[{}, {}]", c / 4, c % 4)).collect();
+        let outline = format!(r#""cages": [{{"cells": [{}]}}]"#, everything.join(", "));
+        let outlined = text.replace(r#""cages": [{"cells": [[0, 0], [1, 0]]}]"#, &outline);
+        let outlined = from_scl(&outlined).unwrap();
+        assert_eq!(outlined.solution(), puzzle.solution());
+        assert_eq!(outlined.constraints_of::<Cage>().count(), 0);
+        assert!(matches!(from_scl("scl{}").err(), Some(QuadrataError::InvalidFormat(_))));
+        assert!(matches!(from_scl("sudokupad.app/abc").err(), Some(QuadrataError::InvalidFormat(_))));
+    }
+}
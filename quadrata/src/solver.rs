@@ -1,68 +1,44 @@
 use dlx_rs::Sudoku;
 
-use crate::Puzzle;
-use crate::strategies::guess::guess;
+use crate::{all_candidates, generate_boxes, Candidates, Puzzle, QuadrataError, FIXED_MASK};
+use crate::strategies::{next_step, Solution, Strategy};
 
 
-/// This ignores if the field is actually marked as solved.
-pub fn redo_guesses(board: &Puzzle) -> Vec<u16> {
-    let counts = get_counts(&board, true);
-    let mut new_values = vec![0; board.values.len()];
-    for i in 0..board.values.len() {
-        if board.solved[i] {
-            new_values[i] = board.values[i];
-            continue;
-        }
-        'value_iter: for val in 0..board.size {
-            for (cons, count) in board.boxes.iter().zip(&counts) {
-                if count[cons[i] - 1][val] > 0 {
-                    continue 'value_iter;
-                }
-            }
-            new_values[i] |= 1 << val;
-        }
-    }
+/// Recalculate the candidates for every unsolved cell from scratch using the constraints.
+pub fn redo_guesses(board: &Puzzle) -> Vec<Candidates> {
+    let all = all_candidates(board.size);
+    let mut new_values: Vec<Candidates> = (0..board.values.len())
+        .map(|i| if board.solved[i] { board.values[i] } else { all })
+        .collect();
+    prune_candidates(board, &mut new_values);
     new_values
 }
 
-/// Get the number of occurances of each value in each set of constraints.
-pub fn get_counts(board: &Puzzle, only_solved: bool) -> Vec<Vec<Vec<u8>>> {
-    let mut counts = vec![vec![vec![0u8; board.size]; board.size]; board.boxes.len()];
+/// Remove candidates from `values` with every constraint on the board.
+pub fn prune_candidates(board: &Puzzle, values: &mut [Candidates]) {
+    for constraint in board.constraints() {
+        constraint.prune(board, values);
+    }
+}
+
+/// Get the number of occurances of each value in each unit of the board.
+pub fn get_counts(board: &Puzzle, only_solved: bool) -> Vec<Vec<u8>> {
+    let geometry = board.geometry();
+    let mut counts = vec![vec![0u8; board.size]; geometry.n_units()];
     for (i, v) in board.values.iter().enumerate() {
-        if only_solved {
-            if !board.solved[i] {
-                continue;
-            }
-            let val = v.trailing_zeros() as usize;
-            // we loop over every set of constraints in `boxes`
-            for cons in 0..board.boxes.len() {
-                // if the constraint is set to 0; don't bother saving
-                if board.boxes[cons][i] == 0 {
-                    continue;
-                }
-                counts[cons][board.boxes[cons][i] - 1][val] += 1;
-            }
-            continue;
-        }
-        let n_values = v.count_ones();
-        if n_values == 0 {
+        if only_solved && !board.solved[i] {
             continue;
         }
-        for val in 0..board.size {
-            if v & (1 << val) == 0 {
-                continue;
-            }
-            for cons in 0..board.boxes.len() {
-                if board.boxes[cons][i] == 0 {
-                    continue;
-                }
-                counts[cons][board.boxes[cons][i] - 1][val] += 1;
+        for val in (0..board.size).filter(|val| v & (1 << val) != 0) {
+            for unit in geometry.units_of(i) {
+                counts[*unit][val] += 1;
             }
         }
     }
     counts
 }
 
+#[allow(dead_code)]
 pub fn is_valid(board: &Puzzle) -> bool {
     // TODO: get rid of this function? We shouldn't be regenerating the get_counts here
     for value in &board.values {
@@ -70,13 +46,11 @@ pub fn is_valid(board: &Puzzle) -> bool {
             return false;
         }
     }
-    let counts = get_counts(&board, true);
-    for cons in counts {
-        for zone in cons {
-            for value in zone {
-                if value > 1 {
-                    return false;
-                }
+    let counts = get_counts(board, true);
+    for unit in counts {
+        for value in unit {
+            if value > 1 {
+                return false;
             }
         }
     }
@@ -84,11 +58,26 @@ pub fn is_valid(board: &Puzzle) -> bool {
 }
 
 
-pub fn solve(board: &Puzzle) -> Result<Vec<u8>, String> {
-    if board.boxes.len() != 3 {
-        return Err("Only traditional sudoku can be solved".to_string());
-    } else if board.size != 4 && board.size != 9 && board.size != 16 {
-        return Err(format!("Board of size {} can not be solved", board.size));
+/// Find up to `limit` solutions for the board.
+pub fn solutions(board: &Puzzle, limit: usize) -> Result<Vec<Vec<u8>>, QuadrataError> {
+    bounded_solutions(board, limit, usize::MAX)
+}
+
+/// Find up to `limit` solutions for the board, giving up with a `SearchLimit` error if the
+/// backtracking search for variant boards tries more than `budget` boards.
+pub fn bounded_solutions(
+    board: &Puzzle,
+    limit: usize,
+    budget: usize,
+) -> Result<Vec<Vec<u8>>, QuadrataError> {
+    if board.size > Candidates::BITS as usize {
+        return Err(QuadrataError::UnsupportedSize(board.size));
+    } else if board.boxes != generate_boxes(board.size)
+        || !board.constraints.is_empty()
+        || ![4, 9, 16].contains(&board.size)
+    {
+        // dancing links only knows plain sudoku of these sizes
+        return search(board, limit, budget);
     }
 
     let mut knowns = Vec::new();
@@ -100,17 +89,257 @@ pub fn solve(board: &Puzzle) -> Result<Vec<u8>, String> {
         knowns.push(board.values[i].trailing_zeros() as usize + 1);
     }
     let sudoku = Sudoku::new_from_input(&knowns);
-    let solutions: Vec<Vec<usize>> = sudoku.collect();
-    if solutions.len() == 0 {
-        return Err("Board has no valid solution".to_string());
+    Ok(sudoku
+        .take(limit)
+        .map(|solution| solution.iter().map(|s| *s as u8).collect())
+        .collect())
+}
+
+/// How many boards the backtracking search tries when sampling solutions to tell them apart,
+/// so a board with a huge search space (like an empty variant grid) is an error instead of
+/// hanging. Solving a board has no limit since a unique puzzle should always load.
+pub const SEARCH_BUDGET: usize = 10_000;
+
+/// Find up to `limit` solutions with a backtracking search that prunes with every constraint.
+///
+/// This is slower than the dancing links solver but can handle any set of constraints.
+fn search(board: &Puzzle, limit: usize, budget: usize) -> Result<Vec<Vec<u8>>, QuadrataError> {
+    let mut board = board.clone();
+    for i in 0..board.values.len() {
+        // like the dancing links solver, anything with a single candidate is a known value
+        board.solved[i] = board.values[i].count_ones() == 1;
+    }
+    board.values = redo_guesses(&board);
+    let mut found = Vec::new();
+    let mut tried = 0;
+    search_from(&mut board, limit, budget, &mut found, &mut tried);
+    if tried > budget {
+        return Err(QuadrataError::SearchLimit);
+    }
+    Ok(found)
+}
+
+fn search_from(
+    board: &mut Puzzle,
+    limit: usize,
+    budget: usize,
+    found: &mut Vec<Vec<u8>>,
+    tried: &mut usize,
+) {
+    *tried += 1;
+    if *tried > budget {
+        return;
+    }
+    // prune until nothing changes since each constraint can open up more pruning for others
+    loop {
+        let mut values = board.values.clone();
+        prune_candidates(board, &mut values);
+        if values == board.values {
+            break;
+        }
+        board.values = values;
+    }
+    if board.constraints().any(|c| !c.conflicts(board).is_empty()) {
+        return;
+    }
+
+    let mut best_cell = None;
+    let mut best_count = u32::MAX;
+    for i in (0..board.values.len()).filter(|i| !board.solved[*i]) {
+        let count = board.values[i].count_ones();
+        if count < best_count {
+            best_cell = Some(i);
+            best_count = count;
+        }
+    }
+    let Some(cell) = best_cell else {
+        found.push(board.to_grid());
+        return;
+    };
+
+    let candidates = board.values[cell];
+    for val in (0..board.size).filter(|val| candidates & (1 << val) != 0) {
+        let mut next = board.clone();
+        next.values[cell] = 1 << val;
+        next.solved[cell] = true;
+        search_from(&mut next, limit, budget, found, tried);
+        if found.len() >= limit || *tried > budget {
+            return;
+        }
+    }
+}
+
+pub fn solve(board: &Puzzle) -> Result<Vec<u8>, QuadrataError> {
+    let mut solutions = solutions(board, 2)?;
+    if solutions.is_empty() {
+        return Err(QuadrataError::NoSolution);
     } else if solutions.len() > 1 {
-        return Err("Board has multiple valid solutions".to_string());
+        return Err(QuadrataError::MultipleSolutions { cells: ambiguous_cells(&solutions) });
     }
-    Ok(solutions[0].iter().map(|s| *s as u8).collect())
+    Ok(solutions.remove(0))
+}
+
+/// Get the cells that have different values in at least two of the solutions.
+pub fn ambiguous_cells(solutions: &[Vec<u8>]) -> Vec<usize> {
+    let Some(first) = solutions.first() else {
+        return Vec::new();
+    };
+    (0..first.len())
+        .filter(|i| solutions.iter().any(|s| s[*i] != first[*i]))
+        .collect()
+}
+
+/// How many solutions to sample when looking for the cells that tell them apart.
+const AMBIGUITY_SAMPLES: usize = 64;
+
+/// Suggest extra givens (cell and value) that would make the board's solution unique.
+///
+/// Givens are added greedily, always picking the cell that takes the most distinct values
+/// across a sample of the solutions, and then any given that turns out to be redundant is
+/// dropped again so that no suggestion can be removed without losing uniqueness.
+pub fn suggest_givens(board: &Puzzle) -> Result<Vec<(usize, u8)>, QuadrataError> {
+    let sampled = bounded_solutions(board, AMBIGUITY_SAMPLES, SEARCH_BUDGET)?;
+    let Some(target) = sampled.first().cloned() else {
+        return Err(QuadrataError::NoSolution);
+    };
+
+    let mut board = board.clone();
+    let mut givens: Vec<(usize, u8)> = Vec::new();
+    let mut sampled = sampled;
+    while sampled.len() > 1 {
+        let best_cell = ambiguous_cells(&sampled)
+            .into_iter()
+            .max_by_key(|i| {
+                let mut seen = 0u32;
+                for s in &sampled {
+                    seen |= 1 << s[*i];
+                }
+                (seen.count_ones(), std::cmp::Reverse(*i))
+            })
+            .expect("multiple solutions must differ in at least one cell");
+        board.values[best_cell] = 1 << (target[best_cell] - 1);
+        givens.push((best_cell, target[best_cell]));
+        sampled = bounded_solutions(&board, AMBIGUITY_SAMPLES, SEARCH_BUDGET)?;
+    }
+
+    // the greedy choices can make earlier givens unnecessary so try removing each one again
+    let mut ix = 0;
+    while ix < givens.len() {
+        let (cell, _) = givens[ix];
+        let saved = board.values[cell];
+        board.values[cell] = 0;
+        if bounded_solutions(&board, 2, SEARCH_BUDGET)?.len() == 1 {
+            givens.remove(ix);
+        } else {
+            board.values[cell] = saved;
+            ix += 1;
+        }
+    }
+    Ok(givens)
+}
+
+/// Copy a board with everything but the fixed (given) cells cleared.
+fn givens_only(board: &Puzzle) -> Puzzle {
+    let mut board = board.clone();
+    for i in 0..board.values.len() {
+        if board.types[i] & FIXED_MASK == 0 {
+            board.values[i] = 0;
+            board.solved[i] = false;
+        }
+    }
+    board
+}
+
+/// Check that every given is needed for the board to have a unique solution.
+pub fn is_minimal(board: &Puzzle) -> Result<bool, QuadrataError> {
+    let mut board = givens_only(board);
+    solve(&board)?;
+    for i in 0..board.values.len() {
+        if !board.solved[i] {
+            continue;
+        }
+        let saved = board.values[i];
+        board.values[i] = 0;
+        if solutions(&board, 2)?.len() == 1 {
+            return Ok(false);
+        }
+        board.values[i] = saved;
+    }
+    Ok(true)
+}
+
+/// Find a set of givens that can be removed while keeping the solution unique.
+///
+/// Givens are tried in cell order and each one is dropped if the board stays unique without
+/// it, so the remaining givens are minimal (although not necessarily the smallest such set).
+pub fn minimize(board: &Puzzle) -> Result<Vec<usize>, QuadrataError> {
+    minimize_in_order(board, 0..board.values.len())
+}
+
+/// Like `minimize`, but trying to remove the givens in the order of `cells`.
+pub fn minimize_in_order(
+    board: &Puzzle,
+    cells: impl IntoIterator<Item = usize>,
+) -> Result<Vec<usize>, QuadrataError> {
+    let mut board = givens_only(board);
+    solve(&board)?;
+    let mut removed = Vec::new();
+    for i in cells {
+        if !board.solved[i] {
+            continue;
+        }
+        let saved = board.values[i];
+        board.values[i] = 0;
+        if solutions(&board, 2)?.len() == 1 {
+            removed.push(i);
+        } else {
+            board.values[i] = saved;
+        }
+    }
+    Ok(removed)
+}
+
+/// Solve the board step by step with the strategies, returning each step taken.
+///
+/// Guesses are resolved with the board's known solution so the path always ends up solved.
+pub fn logical_solve(board: &Puzzle) -> Vec<Solution> {
+    let mut board = board.clone();
+    board.values = redo_guesses(&board);
+    let mut steps = Vec::new();
+    while let Some(mut step) = next_step(&board) {
+        if step.strategy == Strategy::Guess {
+            for (cell, value) in step.values.iter_mut() {
+                if board.truths[*cell] != 0 {
+                    *value = 1 << (board.truths[*cell] - 1);
+                }
+            }
+        }
+        for (cell, value) in &step.values {
+            board.values[*cell] = *value;
+            if value.count_ones() == 1 {
+                board.solved[*cell] = true;
+            }
+        }
+        let updated = redo_guesses(&board);
+        for (value, updated) in board.values.iter_mut().zip(&updated) {
+            *value &= updated;
+        }
+        steps.push(step);
+    }
+    steps
+}
+
+/// Rate how hard the board is by the hardest strategy needed to solve it.
+pub fn rate(board: &Puzzle) -> u32 {
+    logical_solve(board)
+        .iter()
+        .map(|step| step.strategy.difficulty())
+        .max()
+        .unwrap_or(0)
 }
 
 // TODO: fix this up?
-// pub fn solve(board: &Puzzle) -> Result<Vec<u16>, String> {
+// pub fn solve(board: &Puzzle) -> Result<Vec<Candidates>, QuadrataError> {
 //     if !is_valid(board) {
 //         return Err("Starting grid is invalid".to_string());
 //     }
@@ -143,16 +372,17 @@ mod tests {
         board.values[4] = 5;
 
         let counts = get_counts(&board, true);
+        // the rows, then the columns, then the boxes
         assert_eq!(counts, vec![
-                vec![vec![1, 0, 0, 0], vec![0, 1, 0, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 1]],
-                vec![vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![1, 1, 1, 1]],
-                vec![vec![0, 0, 0, 0], vec![1, 1, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 1, 1]]
+                vec![1, 0, 0, 0], vec![0, 1, 0, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 1],
+                vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![1, 1, 1, 1],
+                vec![0, 0, 0, 0], vec![1, 1, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 1, 1],
         ]);
         let counts = get_counts(&board, false);
         assert_eq!(counts, vec![
-                vec![vec![1, 0, 0, 0], vec![1, 1, 1, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 1]],
-                vec![vec![1, 0, 1, 0], vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![1, 1, 1, 1]],
-                vec![vec![1, 0, 1, 0], vec![1, 1, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 1, 1]]
+                vec![1, 0, 0, 0], vec![1, 1, 1, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 1],
+                vec![1, 0, 1, 0], vec![0, 0, 0, 0], vec![0, 0, 0, 0], vec![1, 1, 1, 1],
+                vec![1, 0, 1, 0], vec![1, 1, 0, 0], vec![0, 0, 0, 0], vec![0, 0, 1, 1],
         ]);
     }
 
@@ -174,11 +404,107 @@ mod tests {
 
         let board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
         let solution = solve(&board);
-        assert_eq!(solution, Err("Board has multiple valid solutions".to_string()));
+        assert!(matches!(solution, Err(QuadrataError::MultipleSolutions { .. })));
+
+        // the 1s and 2s in the top left of the first and third rows can swap places
+        let board = Puzzle::raw_from_grid(&vec![0, 0, 3, 4, 3, 4, 1, 2, 0, 0, 4, 3, 4, 3, 2, 1]);
+        let solution = solve(&board);
+        assert_eq!(solution, Err(QuadrataError::MultipleSolutions { cells: vec![0, 1, 8, 9] }));
+
+        let board = Puzzle::raw_from_grid(&vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let solutions = solutions(&board, 3);
+        assert_eq!(solutions.map(|s| s.len()), Ok(3));
 
         // TODO: dlx_rs panics on the following
         // let board = Puzzle::from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
         // let solution = solve(&board);
-        // assert_eq!(solution, Err("Board has no valid solution".to_string()));
+        // assert_eq!(solution, Err(QuadrataError::NoSolution));
+    }
+
+    #[test]
+    fn test_ambiguous_cells() {
+        let solutions = vec![
+            vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1],
+            vec![1, 2, 3, 4, 3, 4, 1, 2, 2, 3, 4, 1, 4, 1, 2, 3],
+        ];
+        assert_eq!(ambiguous_cells(&solutions), vec![9, 11, 13, 15]);
+        assert_eq!(ambiguous_cells(&solutions[..1]), Vec::<usize>::new());
+        assert_eq!(ambiguous_cells(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_suggest_givens() {
+        let mut board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let givens = suggest_givens(&board).unwrap();
+        assert!(!givens.is_empty());
+        for (cell, value) in &givens {
+            assert_eq!(board.values[*cell], 0);
+            board.values[*cell] = 1 << (value - 1);
+        }
+        assert!(solve(&board).is_ok());
+
+        let board = Puzzle::raw_from_grid(&vec![2, 0, 0, 0, 0, 1, 0, 2, 0, 0, 3, 0, 0, 0, 0, 4]);
+        assert_eq!(suggest_givens(&board), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_minimize() {
+        let board = Puzzle::raw_from_grid(&vec![2, 0, 0, 0, 0, 1, 0, 2, 0, 0, 3, 0, 0, 0, 0, 4]);
+        assert_eq!(is_minimal(&board), Ok(true));
+        assert_eq!(minimize(&board), Ok(vec![]));
+
+        let board = Puzzle::raw_from_grid(&vec![2, 4, 0, 0, 0, 1, 0, 2, 0, 0, 3, 0, 0, 0, 0, 4]);
+        assert_eq!(is_minimal(&board), Ok(false));
+        let removed = minimize(&board).unwrap();
+        assert_eq!(removed.len(), 1);
+
+        let board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(matches!(is_minimal(&board), Err(QuadrataError::MultipleSolutions { .. })));
+    }
+
+    #[test]
+    fn test_logical_solve() {
+        let board = Puzzle::from_grid(&vec![2, 0, 0, 0, 0, 1, 0, 2, 0, 0, 3, 0, 0, 0, 0, 4]).unwrap();
+        let steps = logical_solve(&board);
+        assert_eq!(steps.len(), 11);
+        assert!(steps.iter().all(|s| s.strategy != Strategy::Guess));
+        assert_eq!(rate(&board), 2);
+
+        let mut solved = board.clone();
+        for step in steps {
+            for (cell, value) in step.values {
+                solved.values[cell] = value;
+                solved.solved[cell] = true;
+            }
+        }
+        assert!(solved.is_complete());
+    }
+
+    #[test]
+    fn test_search() {
+        let board = Puzzle::raw_from_grid(&vec![2, 0, 0, 0, 0, 1, 0, 2, 0, 0, 3, 0, 0, 0, 0, 4]);
+        assert_eq!(search(&board, 2, SEARCH_BUDGET), Ok(vec![vec![2, 4, 1, 3, 3, 1, 4, 2, 4, 2, 3, 1, 1, 3, 2, 4]]));
+
+        let board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(search(&board, 2, SEARCH_BUDGET), Ok(Vec::new()));
+
+        let board = Puzzle::raw_from_grid(&[0; 16]);
+        assert_eq!(search(&board, 5, SEARCH_BUDGET).map(|s| s.len()), Ok(5));
+
+        // an empty anti-knight grid has too many dead ends to sample its solutions
+        let mut board = Puzzle::raw_from_grid(&[0; 81]);
+        board.add_anti_knight();
+        assert_eq!(board.ambiguous_cells(), Err(QuadrataError::SearchLimit));
+    }
+
+    #[test]
+    fn test_solve_hard_variant() {
+        // Inkala's hard sudoku with a cage that adds nothing still has to load, however many
+        // guesses the backtracking search takes
+        let grid = "800000000003600000070090200050007000000045700000100030001000068008500010090000400";
+        let cage = "cage 45 r1c1 r1c2 r1c3 r1c4 r1c5 r1c6 r1c7 r1c8 r1c9";
+        let puzzle = crate::from_variant(&format!("{}\n{}", grid, cage));
+        let plain = crate::from_81(grid).unwrap();
+        assert_eq!(puzzle.map(|p| p.solution()), Ok(plain.solution()));
     }
 }
@@ -3,8 +3,12 @@ use crate::strategies::{Solution, Strategy};
 
 
 pub fn guess(board: &Puzzle) -> Option<Solution> {
+    if board.values.contains(&0) {
+        // a cell with no candidates left means the board is already broken
+        return None;
+    }
     let mut easiest_cell = 0;
-    let mut n_values_min = 10;
+    let mut n_values_min = u32::MAX;
     for i in 0..board.values.len() {
         let n_values = board.values[i].count_ones();
         if n_values == 1 {
@@ -23,7 +27,7 @@ pub fn guess(board: &Puzzle) -> Option<Solution> {
             n_values_min = n_values;
         }
     }
-    if n_values_min == 10 {
+    if n_values_min == u32::MAX {
         return None;
     }
     Some(Solution {
@@ -49,5 +53,17 @@ mod tests {
             strategy: Strategy::Guess,
             guide_cells: vec![],
         }));
+
+        board.values[15] = 0;
+        assert_eq!(guess(&board), None);
+    }
+
+    #[test]
+    fn test_guess_large() {
+        // every open cell of a 16x16 can have more than 9 candidates
+        let mut board = Puzzle::raw_from_grid(&[0; 256]);
+        board.values = vec![0xFFFF; 256];
+        board.values[7] = 0x0FFF;
+        assert_eq!(guess(&board).map(|s| s.values), Some(vec![(7, 1)]));
     }
 }
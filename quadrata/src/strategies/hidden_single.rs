@@ -4,22 +4,19 @@ use crate::strategies::{Solution, Strategy};
 
 
 pub fn hidden_single(board: &Puzzle) -> Option<Solution> {
-    let counts = get_counts(&board, false);
+    let counts = get_counts(board, false);
     for i in 0..board.values.len() {
         let n_guesses = board.values[i].count_ones();
         if n_guesses <= 1 {
             // skip empty cell too even though we should probably error
             continue;
         }
-        for cons in 0..board.boxes.len() {
-            if board.boxes[cons][i] == 0 {
-                continue;
-            }
-            for val in 0..board.size {
+        for unit in board.geometry().units_of(i) {
+            for (val, n_cells) in counts[*unit].iter().enumerate() {
                 if board.values[i] & (1 << val) == 0 {
                     continue;
                 }
-                if counts[cons][board.boxes[cons][i] - 1][val] == 1 {
+                if *n_cells == 1 {
                     return Some(Solution {
                         values: vec![(i, 1 << val)],
                         strategy: Strategy::HiddenSingle,
@@ -0,0 +1,114 @@
+use crate::constraints::cage::Cage;
+use crate::Puzzle;
+use crate::strategies::{Solution, Strategy};
+
+
+/// The "rule of 45": the digits in a region always add up to the same total, so if the cages
+/// inside a region cover all but one of its open cells (an innie), or the cages overlapping a
+/// region stick out of it by only one open cell (an outie), that cell's value is known.
+pub fn innies_outies(board: &Puzzle) -> Option<Solution> {
+    let cages: Vec<&Cage> = board.constraints_of::<Cage>().filter(|c| c.sum != 0).collect();
+    if cages.is_empty() {
+        return None;
+    }
+    let total = (board.size * (board.size + 1) / 2) as i64;
+    let value_of = |i: usize| (board.values[i].trailing_zeros() + 1) as i64;
+
+    let geometry = board.geometry();
+    for unit in 0..geometry.n_units() {
+        let region = geometry.cells_in(unit);
+        let in_region = |i: usize| geometry.units_of(i).contains(&unit);
+        if region.len() != board.size {
+            continue;
+        }
+        let mut inside_sum = 0;
+        let mut covered = vec![false; board.values.len()];
+        let mut overlap_sum = 0;
+        let mut overlapping = vec![false; board.values.len()];
+        for cage in &cages {
+            let n_inside = cage.cells.iter().filter(|i| in_region(**i)).count();
+            if n_inside == 0 {
+                continue;
+            }
+            overlap_sum += cage.sum as i64;
+            for i in &cage.cells {
+                overlapping[*i] = true;
+            }
+            if n_inside == cage.cells.len() {
+                inside_sum += cage.sum as i64;
+                for i in &cage.cells {
+                    covered[*i] = true;
+                }
+            }
+        }
+
+        // innies: the region cells not in a cage entirely inside the region
+        let innies: Vec<usize> = region.iter().copied().filter(|i| !covered[*i]).collect();
+        let open: Vec<usize> = innies.iter().copied().filter(|i| !board.solved[*i]).collect();
+        if open.len() == 1 {
+            let known: i64 = innies.iter().filter(|i| board.solved[**i]).map(|i| value_of(*i)).sum();
+            if let Some(solution) = deduce(board, open[0], total - inside_sum - known, region) {
+                return Some(solution);
+            }
+        }
+
+        // outies: the cells of overlapping cages that stick out of the region
+        if region.iter().all(|i| overlapping[*i]) {
+            let outies: Vec<usize> = (0..board.values.len())
+                .filter(|i| overlapping[*i] && !in_region(*i))
+                .collect();
+            let open: Vec<usize> = outies.iter().copied().filter(|i| !board.solved[*i]).collect();
+            if open.len() == 1 {
+                let known: i64 = outies.iter().filter(|i| board.solved[**i]).map(|i| value_of(*i)).sum();
+                if let Some(solution) = deduce(board, open[0], overlap_sum - total - known, region) {
+                    return Some(solution);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn deduce(board: &Puzzle, cell: usize, value: i64, region: &[usize]) -> Option<Solution> {
+    if value < 1 || value > board.size as i64 || board.values[cell] & (1 << (value - 1)) == 0 {
+        return None;
+    }
+    Some(Solution {
+        values: vec![(cell, 1 << (value - 1))],
+        strategy: Strategy::InniesOuties,
+        guide_cells: region.to_vec(),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::redo_guesses;
+
+    #[test]
+    fn test_innies_outies() {
+        let mut board = Puzzle::raw_from_grid(&[0; 16]);
+        board.add_constraint(Cage { cells: vec![0, 1, 2], sum: 6 });
+        board.values = redo_guesses(&board);
+        // the first row adds up to 10 so the last cell of it has to be a 4
+        let solution = innies_outies(&board);
+        assert_eq!(solution, Some(Solution {
+            values: vec![(3, 8)],
+            strategy: Strategy::InniesOuties,
+            guide_cells: vec![0, 1, 2, 3],
+        }));
+
+        let mut board = Puzzle::raw_from_grid(&[0; 16]);
+        board.add_constraint(Cage { cells: vec![0, 1], sum: 3 });
+        board.add_constraint(Cage { cells: vec![2, 3, 7], sum: 9 });
+        board.values = redo_guesses(&board);
+        // the cages covering the first row add up to 12 so the cell sticking out is a 2
+        let solution = innies_outies(&board);
+        assert_eq!(solution, Some(Solution {
+            values: vec![(7, 2)],
+            strategy: Strategy::InniesOuties,
+            guide_cells: vec![0, 1, 2, 3],
+        }));
+    }
+}
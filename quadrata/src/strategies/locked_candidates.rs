@@ -0,0 +1,123 @@
+use crate::{Candidates, Puzzle};
+use crate::strategies::{Solution, Strategy};
+
+
+/// If every place a value can go in one region is also inside a second region, the value has
+/// to be in their overlap and can be removed from the rest of the second region.
+///
+/// This is the usual pointing/claiming between boxes and lines, but it works for any pair of
+/// region sets so extra regions (diagonals, windows, etc) are picked up too. More generally,
+/// any cell that's a peer of every place a value can go in a region can't have the value, which
+/// picks up rules without regions like anti-knight.
+pub fn locked_candidates(board: &Puzzle) -> Option<Solution> {
+    let geometry = board.geometry();
+    for a in 0..geometry.n_units() {
+        let cells: Vec<usize> =
+            geometry.cells_in(a).iter().copied().filter(|i| !board.solved[*i]).collect();
+        for val in 0..board.size {
+            let with_val: Vec<usize> = cells
+                .iter()
+                .copied()
+                .filter(|i| board.values[*i] & (1 << val) != 0)
+                .collect();
+            if with_val.is_empty() {
+                continue;
+            }
+            for b in geometry.units_of(with_val[0]).iter().copied() {
+                if a == b || with_val.iter().any(|i| !geometry.units_of(*i).contains(&b)) {
+                    continue;
+                }
+                let values: Vec<(usize, Candidates)> = geometry
+                    .cells_in(b)
+                    .iter()
+                    .copied()
+                    .filter(|i| !geometry.units_of(*i).contains(&a))
+                    .filter(|i| !board.solved[*i] && board.values[*i] & (1 << val) != 0)
+                    .map(|i| (i, board.values[i] & !(1 << val)))
+                    .collect();
+                if !values.is_empty() {
+                    return Some(Solution {
+                        values,
+                        strategy: Strategy::LockedCandidates,
+                        guide_cells: with_val,
+                    });
+                }
+            }
+        }
+    }
+
+    for unit in 0..geometry.n_units() {
+        for val in 0..board.size {
+            let with_val: Vec<usize> = geometry
+                .cells_in(unit)
+                .iter()
+                .copied()
+                .filter(|i| !board.solved[*i] && board.values[*i] & (1 << val) != 0)
+                .collect();
+            if with_val.is_empty() {
+                continue;
+            }
+            let values: Vec<(usize, Candidates)> = board
+                .peers(with_val[0])
+                .iter()
+                .copied()
+                .filter(|i| !board.solved[*i] && board.values[*i] & (1 << val) != 0)
+                .filter(|i| with_val.iter().all(|c| c == i || board.peers(*c).binary_search(i).is_ok()))
+                .filter(|i| !with_val.contains(i))
+                .map(|i| (i, board.values[i] & !(1 << val)))
+                .collect();
+            if !values.is_empty() {
+                return Some(Solution {
+                    values,
+                    strategy: Strategy::LockedCandidates,
+                    guide_cells: with_val,
+                });
+            }
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::redo_guesses;
+
+    #[test]
+    fn test_locked_candidates() {
+        let mut board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4]);
+        board.values = redo_guesses(&board);
+        let solution = locked_candidates(&board);
+        assert_eq!(solution, None);
+
+        let mut board = Puzzle::raw_from_grid(&[0; 16]);
+        board.values = redo_guesses(&board);
+        // 1 has to go in r1c1 or r1c2 of the first row so it can't be anywhere else in the box
+        board.values[2] &= !1;
+        board.values[3] &= !1;
+        let solution = locked_candidates(&board);
+        assert_eq!(solution, Some(Solution {
+            values: vec![(4, 14), (5, 14)],
+            strategy: Strategy::LockedCandidates,
+            guide_cells: vec![0, 1],
+        }));
+    }
+
+    #[test]
+    fn test_locked_candidates_peers() {
+        let mut board = Puzzle::raw_from_grid(&[0; 81]);
+        board.add_constraint(crate::AntiKnight);
+        board.values = redo_guesses(&board);
+        // 1 in the first row is at r1c1 or r1c5 and r2c3 sees both (by box and by knight)
+        for i in [1, 2, 3, 5, 6, 7, 8] {
+            board.values[i] &= !1;
+        }
+        let solution = locked_candidates(&board);
+        assert_eq!(solution, Some(Solution {
+            values: vec![(11, 0b111111110)],
+            strategy: Strategy::LockedCandidates,
+            guide_cells: vec![0, 4],
+        }));
+    }
+}
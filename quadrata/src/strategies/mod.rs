@@ -1,18 +1,63 @@
+use std::fmt;
+
+use crate::{Candidates, Puzzle};
+
 pub mod guess;
 pub mod hidden_single;
+pub mod innies_outies;
+pub mod locked_candidates;
+pub mod naked_single;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Strategy {
     HiddenSingle,
+    NakedSingle,
+    LockedCandidates,
+    InniesOuties,
     Guess,
 }
 
-#[derive(Debug, PartialEq)]
+impl Strategy {
+    /// How hard the strategy is for a person to spot; used to rate puzzles.
+    pub fn difficulty(&self) -> u32 {
+        match self {
+            Strategy::HiddenSingle => 1,
+            Strategy::NakedSingle => 2,
+            Strategy::LockedCandidates => 3,
+            Strategy::InniesOuties => 4,
+            Strategy::Guess => 10,
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Strategy::HiddenSingle => "hidden single",
+            Strategy::NakedSingle => "naked single",
+            Strategy::LockedCandidates => "locked candidates",
+            Strategy::InniesOuties => "innies/outies",
+            Strategy::Guess => "guess",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Solution {
     /// A list of cells and their updated guesses.
-    values: Vec<(usize, u16)>,
+    pub values: Vec<(usize, Candidates)>,
     /// Which strategy was used to derive the solution.
-    strategy: Strategy,
+    pub strategy: Strategy,
     /// Which cells guided the solution.
-    guide_cells: Vec<usize>,
+    pub guide_cells: Vec<usize>,
+}
+
+/// Find the next step to take using the easiest strategy that makes progress.
+pub fn next_step(board: &Puzzle) -> Option<Solution> {
+    hidden_single::hidden_single(board)
+        .or_else(|| naked_single::naked_single(board))
+        .or_else(|| locked_candidates::locked_candidates(board))
+        .or_else(|| innies_outies::innies_outies(board))
+        .or_else(|| guess::guess(board))
 }
@@ -0,0 +1,41 @@
+use crate::Puzzle;
+use crate::strategies::{Solution, Strategy};
+
+
+pub fn naked_single(board: &Puzzle) -> Option<Solution> {
+    for i in 0..board.values.len() {
+        if board.solved[i] || board.values[i].count_ones() != 1 {
+            continue;
+        }
+        return Some(Solution {
+            values: vec![(i, board.values[i])],
+            strategy: Strategy::NakedSingle,
+            guide_cells: vec![],
+        });
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::redo_guesses;
+
+    #[test]
+    fn test_naked_single() {
+        let mut board = Puzzle::raw_from_grid(&vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4]);
+        board.values = redo_guesses(&board);
+        let solution = naked_single(&board);
+        assert_eq!(solution, None);
+
+        let mut board = Puzzle::raw_from_grid(&vec![1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        board.values = redo_guesses(&board);
+        let solution = naked_single(&board);
+        assert_eq!(solution, Some(Solution {
+            values: vec![(3, 8)],
+            strategy: Strategy::NakedSingle,
+            guide_cells: vec![],
+        }));
+    }
+}
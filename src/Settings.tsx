@@ -6,6 +6,8 @@ import {
 } from '@mantine/core';
 import { Dispatch, SetStateAction } from 'react';
 
+import { AutoPencil } from '../quadrata/pkg/quadrata';
+
 
 export enum ErrorHandling {
   Allow = 'allow',
@@ -15,14 +17,6 @@ export enum ErrorHandling {
   ShowInconsistent = 'show_inconsistent',
 }
 
-// TODO: remove this once https://github.com/rustwasm/wasm-bindgen/issues/3057 is fixed
-export enum AutoPencil {
-  Never = 'never',
-  OnlyRemove = 'onlyremove',
-  Always = 'always',
-  Snyder = 'synder',
-}
-
 export interface Settings {
   errorHandling: ErrorHandling;
   autoPencil: AutoPencil;
@@ -69,16 +63,16 @@ export function SettingsPane(props: {settings: Settings, setSettings: Dispatch<S
     />
     <NativeSelect
       label="Automatically Pencil"
-      value={settings.autoPencil}
+      value={String(settings.autoPencil)}
       onChange={evt => {
-        settings.autoPencil = evt.currentTarget.value as AutoPencil;
+        settings.autoPencil = Number(evt.currentTarget.value) as AutoPencil;
         setSettings({...settings});
       }}
       data={[
-        { label: 'Never', value: AutoPencil.Never },
-        { label: 'Allow', value: AutoPencil.Always },
-        { label: 'Only Remove', value: AutoPencil.OnlyRemove },
-        { label: 'Snyder', value: AutoPencil.Snyder },
+        { label: 'Never', value: String(AutoPencil.Never) },
+        { label: 'Allow', value: String(AutoPencil.Always) },
+        { label: 'Only Remove', value: String(AutoPencil.OnlyRemove) },
+        { label: 'Snyder', value: String(AutoPencil.Snyder) },
       ]}
     />
     <br />
@@ -5,9 +5,9 @@ import { useState, StrictMode } from 'react'
 import ReactDOM from 'react-dom/client'
 import { useLocalStorage } from "@uidotdev/usehooks";
 
-import { init, from_81, Puzzle } from '../quadrata/pkg/quadrata';
+import { init, from_81, AutoPencil, Puzzle } from '../quadrata/pkg/quadrata';
 import Grid from './Grid.tsx'
-import { AutoPencil, DEFAULT_SETTINGS, SettingsPane } from './Settings';
+import { DEFAULT_SETTINGS, SettingsPane } from './Settings';
 import './index.css'
 
 import '@mantine/core/styles.css';